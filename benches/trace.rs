@@ -1,4 +1,4 @@
-use batch_tracing::{new_span, root_scope};
+use batch_tracing::{new_span, root_scope, CollectOptions};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 fn trace_wide_bench(c: &mut Criterion) {
@@ -19,7 +19,7 @@ fn trace_wide_bench(c: &mut Criterion) {
 
                     collector
                 }
-                .collect(false, None, None);
+                .collect(CollectOptions::new());
 
                 black_box(r);
             });
@@ -306,7 +306,8 @@ impl<T> FixedIndexQueue<T> {
         self.internal.clear();
     }
 
-    /// Removes values before `index`. Should make sure `index` is valid.
+    /// Removes values before `index`. Should make sure `index` is valid or the next index,
+    /// which removes all values.
     ///
     /// # Examples
     /// ```
@@ -319,14 +320,19 @@ impl<T> FixedIndexQueue<T> {
     ///
     /// queue.remove_before(2);
     /// assert_eq!(queue.len(), 1);
+    /// assert_eq!(&queue[2], &43);
     /// ```
     #[inline]
     pub fn remove_before(&mut self, index: usize) {
-        assert!(self.idx_is_valid(index), "index {} isn't valid", index);
+        assert!(
+            index.wrapping_sub(self.offset) <= self.internal.len(),
+            "index {} isn't valid",
+            index
+        );
 
         let mut count = index.wrapping_sub(self.offset);
         while count > 0 {
-            self.internal.pop_front();
+            self.pop_front();
             count -= 1;
         }
     }
@@ -1,13 +1,13 @@
 // Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
 
-use crate::{new_span, spawn_scope, Scope};
+use crate::{new_span, spawn_scope, Scope, SpanName};
 use std::task::Poll;
 
 impl<T: Sized> FutureExt for T {}
 
 pub trait FutureExt: Sized {
     #[inline]
-    fn in_new_scope(self, event: &'static str) -> WithScope<Self> {
+    fn in_new_scope(self, event: impl Into<SpanName>) -> WithScope<Self> {
         WithScope {
             inner: self,
             scope: spawn_scope(event),
@@ -20,8 +20,11 @@ pub trait FutureExt: Sized {
     }
 
     #[inline]
-    fn in_new_span(self, event: &'static str) -> WithSpan<Self> {
-        WithSpan { inner: self, event }
+    fn in_new_span(self, event: impl Into<SpanName>) -> WithSpan<Self> {
+        WithSpan {
+            inner: self,
+            event: event.into(),
+        }
     }
 }
 
@@ -68,7 +71,7 @@ impl<T: futures_01::Future> futures_01::Future for WithScope<T> {
 pub struct WithSpan<T> {
     #[pin]
     inner: T,
-    event: &'static str,
+    event: SpanName,
 }
 
 impl<T: std::future::Future> std::future::Future for WithSpan<T> {
@@ -76,7 +79,7 @@ impl<T: std::future::Future> std::future::Future for WithSpan<T> {
 
     fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
-        let _guard = new_span(this.event);
+        let _guard = new_span(this.event.clone());
         this.inner.poll(cx)
     }
 }
@@ -86,7 +89,7 @@ impl<T: futures_01::Future> futures_01::Future for WithSpan<T> {
     type Error = T::Error;
 
     fn poll(&mut self) -> futures_01::Poll<Self::Item, Self::Error> {
-        let _guard = new_span(self.event);
+        let _guard = new_span(self.event.clone());
         self.inner.poll()
     }
 }
@@ -1,42 +1,119 @@
 #![feature(map_first_last)]
 #![feature(negative_impls)]
 
+use std::borrow::Cow;
 use std::sync::Arc;
 
 pub use crate::local::scope_guard::LocalScopeGuard;
 pub use crate::local::span_guard::LocalSpanGuard;
+use crate::sampling::HeadSampler;
 use crate::span::span_id::DefaultIdGenerator;
-pub use crate::span::span_id::SpanId;
-pub use crate::span::Span;
-pub use crate::trace::collector::Collector;
-pub use crate::trace::scope::Scope;
+pub use crate::span::span_id::{SpanId, TraceId};
+pub use crate::span::{Log, Logs, PropertyValue, RefKind, Span, SpanName};
+use crate::trace::acquirer::SpanSender;
+pub use crate::trace::collector::{CollectOptions, Collector, Trace, TraceStats};
+pub use crate::trace::scope::{Scope, TraceOptions};
+use futures_03::task::AtomicWaker;
 use std::sync::atomic::AtomicBool;
 
 pub mod collections;
 pub mod future;
 pub mod report;
+pub mod sampling;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub use batch_tracing_macro::{trace, trace_async};
 
 pub(crate) mod local;
 pub(crate) mod span;
 pub(crate) mod trace;
 
-pub fn root_scope(event: &'static str) -> (Scope, Collector) {
+pub fn root_scope(event: impl Into<SpanName>) -> (Scope, Collector) {
+    root_scope_with_options(event, TraceOptions::new())
+}
+
+/// Like [`root_scope`], with limits on the spans the trace records.
+pub fn root_scope_with_options(
+    event: impl Into<SpanName>,
+    options: TraceOptions,
+) -> (Scope, Collector) {
+    let trace_id = DefaultIdGenerator::next_trace_id();
+    new_trace(trace_id, event.into(), options)
+}
+
+/// Like [`root_scope`], but only records the trace if `sampler` samples it.
+///
+/// Otherwise the returned scope is empty, so that spans and scopes started from it cost next to
+/// nothing, and the collector returns no spans at once.
+pub fn root_scope_with_sampler<S: HeadSampler + ?Sized>(
+    event: impl Into<SpanName>,
+    sampler: &S,
+) -> (Scope, Collector) {
+    let trace_id = DefaultIdGenerator::next_trace_id();
+    let event = event.into();
+    if sampler.sample(trace_id, &event) {
+        return new_trace(trace_id, event, TraceOptions::new());
+    }
+
+    // dropping the sender right away leaves nothing to wait for
+    let (_, rx) = crossbeam_channel::unbounded();
+    let closed = Arc::new(AtomicBool::new(true));
+    let collector = Collector::new(trace_id, rx, closed, Arc::new(AtomicWaker::new()));
+    (Scope::default(), collector)
+}
+
+fn new_trace(trace_id: TraceId, event: SpanName, options: TraceOptions) -> (Scope, Collector) {
     let (tx, rx) = crossbeam_channel::unbounded();
     let closed = Arc::new(AtomicBool::new(false));
-    let scope = Scope::new_root_scope(event, tx, Arc::clone(&closed));
-    let collector = Collector::new(rx, closed);
+    let waker = Arc::new(AtomicWaker::new());
+    let sender = SpanSender::new(tx, Arc::clone(&waker), &options);
+    let scope = Scope::new_root_scope(trace_id, event, sender, Arc::clone(&closed));
+    let collector = Collector::new(trace_id, rx, closed, waker);
     (scope, collector)
 }
 
+/// Creates a scope for work handed over to another thread or task. The scope span
+/// `FollowsFrom` the current span, as the current span usually does not wait for it.
 #[inline]
-pub fn spawn_scope(event: &'static str) -> Scope {
-    Scope::new_scope(event)
+pub fn spawn_scope(event: impl Into<SpanName>) -> Scope {
+    Scope::new_scope(event.into(), RefKind::FollowsFrom)
 }
 
+/// Like `spawn_scope`, but lets the caller decide how the scope span refers to the current
+/// span, e.g. `ChildOf` when the spawned work is joined before the current span ends.
 #[inline]
-pub fn new_span(event: &'static str) -> LocalSpanGuard {
-    LocalSpanGuard::new(event)
+pub fn spawn_scope_with_ref_kind(event: impl Into<SpanName>, ref_kind: RefKind) -> Scope {
+    Scope::new_scope(event.into(), ref_kind)
+}
+
+/// Starts a span under the current span. The span ends when the returned guard is dropped.
+///
+/// A `&'static str` name is stored without allocation. Names built at runtime, e.g. from
+/// the method of a request, can be passed as `String`, `Cow` or `Arc<str>`; they are stored
+/// once, so a [`SpanName`] can be cloned to name further spans without allocating.
+#[inline]
+pub fn new_span(event: impl Into<SpanName>) -> LocalSpanGuard {
+    LocalSpanGuard::new(event.into())
+}
+
+/// Records an event with the current time in the innermost span created by `new_span` on
+/// this thread. Does nothing if there is no such span.
+#[inline]
+pub fn log_event(event: impl Into<Cow<'static, str>>) {
+    crate::local::span_guard::log_event(event.into())
+}
+
+/// Like `log_event`, with properties describing the event, e.g. the attempt of a retry.
+#[inline]
+pub fn log_event_with_properties<
+    K: Into<Cow<'static, str>>,
+    I: IntoIterator<Item = (K, PropertyValue)>,
+    F: FnOnce() -> I,
+>(
+    event: impl Into<Cow<'static, str>>,
+    properties: F,
+) {
+    crate::local::span_guard::log_event_with_properties(event.into(), properties)
 }
 
 #[inline]
@@ -47,12 +124,10 @@ pub fn set_span_id_prefix(id_prefix: u32) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::report::Reporter;
+    use crate::report::{Reporter, SpanExporter};
+    use crate::testing::{assert_shape, Tree};
     use crossbeam_utils::sync::WaitGroup;
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
     use std::net::{Ipv4Addr, SocketAddr};
-    use std::time::Instant;
 
     fn four_spans() {
         {
@@ -78,35 +153,84 @@ mod tests {
         }
     }
 
-    fn report(service_name: &'static str, spans: Vec<Span>) {
-        let mut hash = DefaultHasher::new();
-        service_name.hash(&mut hash);
-        Instant::now().hash(&mut hash);
+    fn four_spans_under(event: &str) -> Tree {
+        Tree::new(
+            event,
+            vec![
+                Tree::leaf("iter span"),
+                Tree::leaf("iter span"),
+                Tree::new("rec span", vec![Tree::leaf("rec span")]),
+            ],
+        )
+    }
 
+    fn collect(collector: Collector) -> (TraceId, Vec<Span>) {
+        let trace = collector.collect(CollectOptions::new());
+        (trace.trace_id, trace.spans)
+    }
+
+    fn report(service_name: &'static str, trace_id: TraceId, spans: Vec<Span>) {
         let socket = SocketAddr::new(Ipv4Addr::new(127, 0, 0, 1).into(), 6831);
-        let reporter = Reporter::new(socket, service_name);
-        reporter.report(hash.finish(), spans).ok();
+        let reporter: Box<dyn SpanExporter> = Box::new(Reporter::new(socket, service_name));
+        reporter.export(trace_id, spans).unwrap();
+    }
+
+    #[test]
+    fn trace_id() {
+        let (scope1, collector1) = root_scope("root1");
+        let (scope2, collector2) = root_scope("root2");
+
+        assert_eq!(scope1.trace_id(), Some(collector1.trace_id()));
+        assert_eq!(scope2.trace_id(), Some(collector2.trace_id()));
+        assert_ne!(collector1.trace_id(), collector2.trace_id());
+        assert_eq!(collector1.trace_id().to_string().len(), 32);
+
+        let _sg = scope1.start_scope();
+        assert_eq!(spawn_scope("spawned").trace_id(), None);
+    }
+
+    #[test]
+    fn runtime_names() {
+        let method = String::from("Get");
+        let (scope, collector) = root_scope(format!("rpc {}", method));
+        {
+            let _sg = scope.start_scope();
+            let _g = new_span(method.clone())
+                .with_property(|| (format!("{}.table", method.to_lowercase()), "t1".into()))
+                .with_property(|| ("static", "value".into()));
+        }
+        drop(scope);
+        let spans = collector
+            .collect(CollectOptions::new().with_sync(true))
+            .spans;
+
+        let root = spans.iter().find(|s| s.is_root()).unwrap();
+        let child = spans.iter().find(|s| !s.is_root()).unwrap();
+        assert_eq!(root.event, "rpc Get");
+        assert_eq!(child.event, "Get");
+        assert_eq!(child.properties[0].0, "get.table");
+        assert!(matches!(child.properties[1].0, Cow::Borrowed("static")));
     }
 
     #[test]
     fn single_thread_single_scope() {
-        let spans = {
+        let (trace_id, spans) = collect({
             let (root_scope, collector) = root_scope("root");
             let _sg = root_scope.start_scope();
 
             four_spans();
 
             collector
-        }
-        .collect(false, None, None);
+        });
 
         assert_eq!(spans.len(), 5);
-        report("single_thread_single_scope", spans);
+        assert_shape(&spans, &four_spans_under("root"));
+        report("single_thread_single_scope", trace_id, spans);
     }
 
     #[test]
     fn single_thread_multiple_scopes() {
-        let (spans1, spans2, spans3) = {
+        let ((id1, spans1), (id2, spans2), (id3, spans3)) = {
             let (c1, c2, c3) = {
                 let (root_scope1, collector1) = root_scope("root1");
                 let (root_scope2, collector2) = root_scope("root2");
@@ -121,24 +245,38 @@ mod tests {
                 (collector1, collector2, collector3)
             };
 
-            (
-                c1.collect(false, None, None),
-                c2.collect(false, None, None),
-                c3.collect(false, None, None),
-            )
+            (collect(c1), collect(c2), collect(c3))
         };
 
         assert_eq!(spans1.len(), 5);
         assert_eq!(spans2.len(), 5);
         assert_eq!(spans3.len(), 5);
-        report("single_thread_multiple_scopes1", spans1);
-        report("single_thread_multiple_scopes2", spans2);
-        report("single_thread_multiple_scopes3", spans3);
+        report("single_thread_multiple_scopes1", id1, spans1);
+        report("single_thread_multiple_scopes2", id2, spans2);
+        report("single_thread_multiple_scopes3", id3, spans3);
+    }
+
+    #[test]
+    fn scopes_dropped_out_of_order() {
+        let (root_scope1, collector1) = root_scope("root1");
+        let (root_scope2, collector2) = root_scope("root2");
+        let sg1 = root_scope1.start_scope();
+        four_spans();
+        let sg2 = root_scope2.start_scope();
+        drop(sg1);
+        four_spans();
+        drop(sg2);
+        drop((root_scope1, root_scope2));
+
+        let (_, spans1) = collect(collector1);
+        let (_, spans2) = collect(collector2);
+        assert_shape(&spans1, &four_spans_under("root1"));
+        assert_shape(&spans2, &four_spans_under("root2"));
     }
 
     #[test]
     fn multiple_threads_single_scope() {
-        let spans = {
+        let (trace_id, spans) = collect({
             let (scope, collector) = root_scope("root");
 
             let _sg = scope.start_scope();
@@ -162,16 +300,20 @@ mod tests {
             wg.wait();
 
             collector
-        }
-        .collect(false, None, None);
+        });
 
         assert_eq!(spans.len(), 25);
-        report("multiple_threads_single_scope", spans);
+        let mut expected = four_spans_under("root");
+        for _ in 0..4 {
+            expected.children.push(four_spans_under("cross-thread"));
+        }
+        assert_shape(&spans, &Tree::new("root", expected.children));
+        report("multiple_threads_single_scope", trace_id, spans);
     }
 
     #[test]
     fn multiple_threads_multiple_scopes() {
-        let (spans1, spans2) = {
+        let ((id1, spans1), (id2, spans2)) = {
             let (c1, c2) = {
                 let (scope1, collector1) = root_scope("root1");
                 let (scope2, collector2) = root_scope("root2");
@@ -200,12 +342,12 @@ mod tests {
                 (collector1, collector2)
             };
 
-            (c1.collect(false, None, None), c2.collect(false, None, None))
+            (collect(c1), collect(c2))
         };
 
         assert_eq!(spans1.len(), 25);
         assert_eq!(spans2.len(), 25);
-        report("multiple_threads_multiple_scopes1", spans1);
-        report("multiple_threads_multiple_scopes2", spans2);
+        report("multiple_threads_multiple_scopes1", id1, spans1);
+        report("multiple_threads_multiple_scopes2", id2, spans2);
     }
 }
@@ -1,12 +1,13 @@
 use crate::local::span_line::SPAN_LINE;
+use crate::span::{RefKind, SpanName};
 
 use crate::trace::acquirer::AcquirerGroup;
 
 /// Returns registered acquirers from current thread, or `None` if there're no
 /// registered acquires.
-pub fn registered_acquirer_group(event: &'static str) -> Option<AcquirerGroup> {
+pub fn registered_acquirer_group(event: SpanName, ref_kind: RefKind) -> Option<AcquirerGroup> {
     SPAN_LINE.with(|span_line| {
         let mut span_line = span_line.borrow_mut();
-        span_line.registered_acquirer_group(event)
+        span_line.registered_acquirer_group(event, ref_kind)
     })
 }
@@ -18,6 +18,10 @@ impl Registry {
         self.listeners.is_empty()
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &Listener> + Clone {
+        self.listeners.iter()
+    }
+
     pub fn oldest_listener(&self) -> Option<Listener> {
         self.listeners.first().cloned()
     }
@@ -31,13 +35,15 @@ impl Registry {
 pub struct Listener {
     pub(super) queue_index: usize,
     pub(super) slab_index: usize,
+    pub(super) dropped_before: usize,
 }
 
 impl Listener {
-    pub fn new(queue_index: usize, slab_index: usize) -> Self {
+    pub fn new(queue_index: usize, slab_index: usize, dropped_before: usize) -> Self {
         Listener {
             queue_index,
             slab_index,
+            dropped_before,
         }
     }
 }
@@ -26,8 +26,8 @@ impl Drop for LocalScopeGuard {
         if let Some(listener) = self.listener {
             SPAN_LINE.with(|span_line| {
                 let mut span_line = span_line.borrow_mut();
-                let (acg, spans) = span_line.unregister_and_collect(listener);
-                acg.submit(spans);
+                let (acg, spans, dropped_spans) = span_line.unregister_and_collect(listener);
+                acg.submit(spans, dropped_spans);
             })
         }
     }
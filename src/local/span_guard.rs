@@ -1,5 +1,7 @@
 use crate::local::span_line::{SpanLine, SPAN_LINE};
 use crate::span::span_queue::SpanHandle;
+use crate::span::{PropertyValue, RefKind, SpanName};
+use std::borrow::Cow;
 
 pub struct LocalSpanGuard {
     span_handle: Option<SpanHandle>,
@@ -7,7 +9,7 @@ pub struct LocalSpanGuard {
 
 impl LocalSpanGuard {
     #[inline]
-    pub(crate) fn new(event: &'static str) -> Self {
+    pub(crate) fn new(event: SpanName) -> Self {
         SPAN_LINE.with(|span_line| {
             let mut span_line = span_line.borrow_mut();
             let span_handle = span_line.start_span(event);
@@ -16,20 +18,68 @@ impl LocalSpanGuard {
     }
 
     #[inline]
-    pub fn with_properties<I: IntoIterator<Item = (&'static str, String)>, F: FnOnce() -> I>(
+    pub fn with_properties<
+        K: Into<Cow<'static, str>>,
+        I: IntoIterator<Item = (K, PropertyValue)>,
+        F: FnOnce() -> I,
+    >(
         self,
         properties: F,
     ) -> Self {
         self.with_span_line(move |span_handle, span_line| {
-            span_line.add_properties(span_handle, properties)
+            span_line.add_properties(span_handle, || {
+                properties().into_iter().map(|(k, v)| (k.into(), v))
+            })
         });
         self
     }
 
     #[inline]
-    pub fn with_property<F: FnOnce() -> (&'static str, String)>(self, property: F) -> Self {
+    pub fn with_property<K: Into<Cow<'static, str>>, F: FnOnce() -> (K, PropertyValue)>(
+        self,
+        property: F,
+    ) -> Self {
         self.with_span_line(move |span_handle, span_line| {
-            span_line.add_property(span_handle, property);
+            span_line.add_property(span_handle, || {
+                let (k, v) = property();
+                (k.into(), v)
+            });
+        });
+        self
+    }
+
+    /// Records an event with the current time in the span, e.g. `"retry"` or `"cache miss"`.
+    #[inline]
+    pub fn log_event(&self, event: impl Into<Cow<'static, str>>) {
+        self.with_span_line(move |span_handle, span_line| {
+            span_line.add_log(span_handle, event.into(), Vec::new);
+        });
+    }
+
+    /// Like `log_event`, with properties describing the event.
+    #[inline]
+    pub fn log_event_with_properties<
+        K: Into<Cow<'static, str>>,
+        I: IntoIterator<Item = (K, PropertyValue)>,
+        F: FnOnce() -> I,
+    >(
+        &self,
+        event: impl Into<Cow<'static, str>>,
+        properties: F,
+    ) {
+        self.with_span_line(move |span_handle, span_line| {
+            span_line.add_log(span_handle, event.into(), || {
+                properties().into_iter().map(|(k, v)| (k.into(), v))
+            });
+        });
+    }
+
+    /// Overrides how the span refers to its parent. Spans made by `new_span` are `ChildOf`
+    /// by default.
+    #[inline]
+    pub fn with_ref_kind(self, ref_kind: RefKind) -> Self {
+        self.with_span_line(move |span_handle, span_line| {
+            span_line.set_ref_kind(span_handle, ref_kind);
         });
         self
     }
@@ -59,6 +109,33 @@ impl Drop for LocalSpanGuard {
     }
 }
 
+/// Records an event in the innermost active local span of the current thread.
+#[inline]
+pub fn log_event(event: Cow<'static, str>) {
+    SPAN_LINE.with(|span_line| {
+        let mut span_line = span_line.borrow_mut();
+        span_line.add_log_to_active_span(event, Vec::new);
+    })
+}
+
+/// Like `log_event`, with properties describing the event.
+#[inline]
+pub fn log_event_with_properties<
+    K: Into<Cow<'static, str>>,
+    I: IntoIterator<Item = (K, PropertyValue)>,
+    F: FnOnce() -> I,
+>(
+    event: Cow<'static, str>,
+    properties: F,
+) {
+    SPAN_LINE.with(|span_line| {
+        let mut span_line = span_line.borrow_mut();
+        span_line.add_log_to_active_span(event, || {
+            properties().into_iter().map(|(k, v)| (k.into(), v))
+        });
+    })
+}
+
 impl !Send for LocalSpanGuard {}
 
 impl !Sync for LocalSpanGuard {}
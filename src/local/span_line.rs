@@ -1,7 +1,8 @@
 use crate::local::registry::{Listener, Registry};
+use std::borrow::Cow;
 
 use crate::span::span_queue::{SpanHandle, SpanQueue};
-use crate::span::{ScopeSpan, Span};
+use crate::span::{PropertyValue, RefKind, ScopeSpan, Span, SpanName};
 use crate::trace::acquirer::AcquirerGroup;
 use slab::Slab;
 use std::cell::RefCell;
@@ -16,6 +17,12 @@ pub struct SpanLine {
     span_queue: SpanQueue,
     registry: Registry,
     local_acquirer_groups: Slab<Arc<AcquirerGroup>>,
+
+    // registered groups with span limits, spans are started without checking if there are none
+    limited_groups: usize,
+
+    // spans not started because of the span limits, ever on this thread
+    dropped_spans: usize,
 }
 
 impl SpanLine {
@@ -24,15 +31,22 @@ impl SpanLine {
             span_queue: SpanQueue::new(),
             registry: Registry::default(),
             local_acquirer_groups: Slab::default(),
+            limited_groups: 0,
+            dropped_spans: 0,
         }
     }
 
     #[inline]
-    pub fn start_span(&mut self, event: &'static str) -> Option<SpanHandle> {
+    pub fn start_span(&mut self, event: SpanName) -> Option<SpanHandle> {
         if self.registry.is_empty() {
             return None;
         }
 
+        if self.limited_groups > 0 && !self.take_span() {
+            self.dropped_spans = self.dropped_spans.wrapping_add(1);
+            return None;
+        }
+
         Some(self.span_queue.start_span(event))
     }
 
@@ -50,16 +64,21 @@ impl SpanLine {
             self.registry.len(),
         );
 
+        if acquirer_group.is_limited() {
+            self.limited_groups += 1;
+        }
         let slab_idx = self.local_acquirer_groups.insert(acquirer_group);
-        let l = Listener::new(self.span_queue.next_index(), slab_idx);
+        let l = Listener::new(self.span_queue.next_index(), slab_idx, self.dropped_spans);
         self.registry.register(l);
         l
     }
 
+    /// Returns the spans recorded since `listener` was registered, along with the number of
+    /// spans dropped meanwhile.
     pub fn unregister_and_collect(
         &mut self,
         listener: Listener,
-    ) -> (Arc<AcquirerGroup>, VecDeque<Span>) {
+    ) -> (Arc<AcquirerGroup>, VecDeque<Span>, usize) {
         debug_assert_eq!(
             self.local_acquirer_groups.len(),
             self.registry.len(),
@@ -70,6 +89,9 @@ impl SpanLine {
 
         let acg = self.local_acquirer_groups.remove(listener.slab_index);
         self.registry.unregister(listener);
+        if acg.is_limited() {
+            self.limited_groups -= 1;
+        }
 
         let spans = if self.registry.is_empty() {
             self.span_queue.take_queue_from(listener.queue_index)
@@ -79,13 +101,18 @@ impl SpanLine {
             s
         };
 
-        (acg, spans)
+        let dropped_spans = self.dropped_spans.wrapping_sub(listener.dropped_before);
+        (acg, spans, dropped_spans)
     }
 
     /// Return `None` if there're no registered acquirers, or all acquirers
     /// combined into one group.
-    pub fn registered_acquirer_group(&mut self, event: &'static str) -> Option<AcquirerGroup> {
-        match self.start_scope_span("<spawn>", event) {
+    pub fn registered_acquirer_group(
+        &mut self,
+        event: SpanName,
+        ref_kind: RefKind,
+    ) -> Option<AcquirerGroup> {
+        match self.start_scope_span(SpanName::from("<spawn>"), event, ref_kind) {
             None => None,
             Some(es) => {
                 AcquirerGroup::combine(self.local_acquirer_groups.iter().map(|s| s.1.as_ref()), es)
@@ -94,7 +121,10 @@ impl SpanLine {
     }
 
     #[inline]
-    pub fn add_properties<I: IntoIterator<Item = (&'static str, String)>, F: FnOnce() -> I>(
+    pub fn add_properties<
+        I: IntoIterator<Item = (Cow<'static, str>, PropertyValue)>,
+        F: FnOnce() -> I,
+    >(
         &mut self,
         span_handle: &SpanHandle,
         properties: F,
@@ -103,16 +133,60 @@ impl SpanLine {
     }
 
     #[inline]
-    pub fn add_property<F: FnOnce() -> (&'static str, String)>(
+    pub fn add_property<F: FnOnce() -> (Cow<'static, str>, PropertyValue)>(
         &mut self,
         span_handle: &SpanHandle,
         property: F,
     ) {
         self.span_queue.add_property(span_handle, property);
     }
+
+    #[inline]
+    pub fn add_log<I: IntoIterator<Item = (Cow<'static, str>, PropertyValue)>, F: FnOnce() -> I>(
+        &mut self,
+        span_handle: &SpanHandle,
+        event: Cow<'static, str>,
+        properties: F,
+    ) {
+        self.span_queue.add_log(span_handle, event, properties);
+    }
+
+    #[inline]
+    pub fn add_log_to_active_span<
+        I: IntoIterator<Item = (Cow<'static, str>, PropertyValue)>,
+        F: FnOnce() -> I,
+    >(
+        &mut self,
+        event: Cow<'static, str>,
+        properties: F,
+    ) {
+        if self.registry.is_empty() {
+            return;
+        }
+
+        self.span_queue.add_log_to_active_span(event, properties);
+    }
+
+    #[inline]
+    pub fn set_ref_kind(&mut self, span_handle: &SpanHandle, ref_kind: RefKind) {
+        self.span_queue.set_ref_kind(span_handle, ref_kind);
+    }
 }
 
 impl SpanLine {
+    /// Takes a span from the budgets of the registered groups, along with the spans held for
+    /// each of them.
+    fn take_span(&self) -> bool {
+        let next_index = self.span_queue.next_index();
+        let local_acquirer_groups = &self.local_acquirer_groups;
+        AcquirerGroup::take_span(self.registry.iter().map(|l| {
+            (
+                local_acquirer_groups[l.slab_index].as_ref(),
+                next_index.wrapping_sub(l.queue_index),
+            )
+        }))
+    }
+
     fn gc(&mut self) {
         if let Some(l) = self.registry.oldest_listener() {
             self.span_queue.remove_before(l.queue_index);
@@ -121,13 +195,17 @@ impl SpanLine {
 
     fn start_scope_span(
         &mut self,
-        placeholder_event: &'static str,
-        event: &'static str,
+        placeholder_event: SpanName,
+        event: SpanName,
+        ref_kind: RefKind,
     ) -> Option<ScopeSpan> {
         if self.registry.is_empty() {
             return None;
         }
 
-        Some(self.span_queue.start_scope_span(placeholder_event, event))
+        Some(
+            self.span_queue
+                .start_scope_span(placeholder_event, event, ref_kind),
+        )
     }
 }
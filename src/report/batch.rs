@@ -0,0 +1,335 @@
+use crate::report::{Result, SpanExporter};
+use crate::{Span, TraceId};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+pub struct BatchConfig {
+    queue_capacity: usize,
+    max_batch_size: usize,
+    max_delay: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        BatchConfig {
+            queue_capacity: 2048,
+            max_batch_size: 512,
+            max_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl BatchConfig {
+    /// Sets the number of traces that can wait for the background thread. Traces reported
+    /// while the queue is full are dropped.
+    pub fn with_queue_capacity(mut self, queue_capacity: usize) -> Self {
+        self.queue_capacity = queue_capacity;
+        self
+    }
+
+    /// Sets the number of spans that triggers an export.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Sets how long a span may wait in a batch before the batch is exported.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+}
+
+enum Command {
+    Export(TraceId, Vec<Span>),
+    Flush(Sender<Result<()>>),
+    Shutdown(Sender<Result<()>>),
+}
+
+/// Hands spans over to an exporter running on a background thread.
+///
+/// Spans of many traces are coalesced into one export once `max_batch_size` spans are queued
+/// or the oldest of them has waited for `max_delay`. Reporting never blocks: traces arriving
+/// while the queue is full are dropped and counted in [`dropped_spans`].
+///
+/// [`dropped_spans`]: BatchReporter::dropped_spans
+pub struct BatchReporter {
+    sender: Sender<Command>,
+    dropped_spans: Arc<AtomicUsize>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl BatchReporter {
+    pub fn new<E: SpanExporter + Send + 'static>(exporter: E, config: BatchConfig) -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded(config.queue_capacity);
+        let handle = std::thread::Builder::new()
+            .name("batch-reporter".to_owned())
+            .spawn(move || Worker::new(exporter, config).run(receiver))
+            .expect("failed to spawn the batch reporter thread");
+
+        BatchReporter {
+            sender,
+            dropped_spans: Arc::new(AtomicUsize::new(0)),
+            handle: Mutex::new(Some(handle)),
+        }
+    }
+
+    pub fn report(&self, trace_id: TraceId, spans: Vec<Span>) {
+        let len = spans.len();
+        if self
+            .sender
+            .try_send(Command::Export(trace_id, spans))
+            .is_err()
+        {
+            self.dropped_spans.fetch_add(len, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of spans dropped because the queue was full or the reporter was
+    /// shut down.
+    pub fn dropped_spans(&self) -> usize {
+        self.dropped_spans.load(Ordering::Relaxed)
+    }
+
+    /// Exports all queued spans and flushes the exporter. Blocks until done.
+    ///
+    /// Returns the first error the exporter ran into since the last flush.
+    pub fn flush(&self) -> Result<()> {
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        self.sender
+            .send(Command::Flush(tx))
+            .map_err(|_| "batch reporter is shut down")?;
+        rx.recv().map_err(|_| "batch reporter is shut down")?
+    }
+
+    /// Exports all queued spans, shuts the exporter down and stops the background thread.
+    pub fn shutdown(&self) -> Result<()> {
+        let handle = match self.handle.lock().unwrap().take() {
+            Some(handle) => handle,
+            None => return Ok(()),
+        };
+
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        self.sender
+            .send(Command::Shutdown(tx))
+            .map_err(|_| "batch reporter is shut down")?;
+        let res = rx.recv().map_err(|_| "batch reporter is shut down")?;
+        handle
+            .join()
+            .map_err(|_| "batch reporter thread panicked")?;
+        res
+    }
+}
+
+impl SpanExporter for BatchReporter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report(trace_id, spans);
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        BatchReporter::flush(self)
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        BatchReporter::shutdown(self)
+    }
+}
+
+impl Drop for BatchReporter {
+    fn drop(&mut self) {
+        self.shutdown().ok();
+    }
+}
+
+struct Worker<E> {
+    exporter: E,
+    config: BatchConfig,
+    pending: Vec<(TraceId, Vec<Span>)>,
+    pending_spans: usize,
+    deadline: Option<Instant>,
+    error: Option<Box<dyn std::error::Error + Send + Sync + 'static>>,
+}
+
+impl<E: SpanExporter> Worker<E> {
+    fn new(exporter: E, config: BatchConfig) -> Self {
+        Worker {
+            exporter,
+            config,
+            pending: vec![],
+            pending_spans: 0,
+            deadline: None,
+            error: None,
+        }
+    }
+
+    fn run(mut self, receiver: Receiver<Command>) {
+        loop {
+            let command = match self.deadline {
+                Some(deadline) => {
+                    receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match command {
+                Ok(Command::Export(trace_id, spans)) => {
+                    if self.pending.is_empty() {
+                        self.deadline = Some(Instant::now() + self.config.max_delay);
+                    }
+                    self.pending_spans += spans.len();
+                    self.pending.push((trace_id, spans));
+                    if self.pending_spans >= self.config.max_batch_size {
+                        self.export_pending();
+                    }
+                }
+                Ok(Command::Flush(reply)) => {
+                    self.export_pending();
+                    let res = self.exporter.flush();
+                    reply.send(self.take_error().map_or(res, Err)).ok();
+                }
+                Ok(Command::Shutdown(reply)) => {
+                    self.export_pending();
+                    let res = self.exporter.shutdown();
+                    reply.send(self.take_error().map_or(res, Err)).ok();
+                    return;
+                }
+                Err(RecvTimeoutError::Timeout) => self.export_pending(),
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.export_pending();
+                    self.exporter.shutdown().ok();
+                    return;
+                }
+            }
+        }
+    }
+
+    fn export_pending(&mut self) {
+        self.deadline = None;
+        self.pending_spans = 0;
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let traces = std::mem::take(&mut self.pending);
+        if let Err(e) = self.exporter.export_traces(traces) {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    fn take_error(&mut self) -> Option<Box<dyn std::error::Error + Send + Sync + 'static>> {
+        self.error.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_span;
+    use crate::testing::spans_of;
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        exports: Arc<Mutex<Vec<Vec<u128>>>>,
+        release: Option<Receiver<()>>,
+    }
+
+    impl SpanExporter for Recorder {
+        fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+            self.export_traces(vec![(trace_id, spans)])
+        }
+
+        fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+            if let Some(release) = &self.release {
+                release.recv().ok();
+            }
+            self.exports
+                .lock()
+                .unwrap()
+                .push(traces.into_iter().map(|t| (t.0).0).collect());
+            Ok(())
+        }
+    }
+
+    fn spans(count: usize) -> Vec<Span> {
+        spans_of(|| {
+            for _ in 1..count {
+                let _g = new_span("span");
+            }
+        })
+    }
+
+    #[test]
+    fn coalesce_by_size() {
+        let recorder = Recorder::default();
+        let reporter = BatchReporter::new(
+            recorder.clone(),
+            BatchConfig::default()
+                .with_max_batch_size(10)
+                .with_max_delay(Duration::from_secs(3600)),
+        );
+
+        for trace_id in 0..3 {
+            reporter.report(TraceId::new(trace_id), spans(5));
+        }
+        reporter.flush().unwrap();
+
+        assert_eq!(*recorder.exports.lock().unwrap(), vec![vec![0, 1], vec![2]]);
+        assert_eq!(reporter.dropped_spans(), 0);
+    }
+
+    #[test]
+    fn coalesce_by_time() {
+        let recorder = Recorder::default();
+        let reporter = BatchReporter::new(
+            recorder.clone(),
+            BatchConfig::default().with_max_delay(Duration::from_millis(10)),
+        );
+
+        reporter.report(TraceId::new(0), spans(5));
+        reporter.report(TraceId::new(1), spans(5));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while recorder.exports.lock().unwrap().is_empty() {
+            assert!(Instant::now() < deadline, "batch is never exported");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(*recorder.exports.lock().unwrap(), vec![vec![0, 1]]);
+    }
+
+    #[test]
+    fn drop_when_full() {
+        let (release, rx) = crossbeam_channel::unbounded();
+        let recorder = Recorder {
+            release: Some(rx),
+            ..Recorder::default()
+        };
+        let reporter = BatchReporter::new(
+            recorder.clone(),
+            BatchConfig::default()
+                .with_queue_capacity(1)
+                .with_max_batch_size(1),
+        );
+
+        // at most one trace is being exported and one is waiting in the queue
+        for trace_id in 0..10 {
+            reporter.report(TraceId::new(trace_id), spans(3));
+        }
+        assert!(reporter.dropped_spans() >= 8 * 3);
+
+        for _ in 0..10 {
+            release.send(()).unwrap();
+        }
+        reporter.shutdown().unwrap();
+
+        let exported = recorder.exports.lock().unwrap().len();
+        assert_eq!(exported * 3 + reporter.dropped_spans(), 10 * 3);
+
+        reporter.report(TraceId::new(10), spans(3));
+        assert_eq!(reporter.dropped_spans() + exported * 3, 11 * 3);
+        assert!(reporter.flush().is_err());
+    }
+}
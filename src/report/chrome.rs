@@ -0,0 +1,277 @@
+//! Encodes spans into the [Trace Event Format], which can be loaded by `chrome://tracing`
+//! and [Perfetto](https://ui.perfetto.dev).
+//!
+//! Every scope (the root scope and each spawned scope) is put on its own track, together with
+//! the local spans recorded under it. Local spans recorded by several threads entering the same
+//! scope get further tracks, as spans on one track must nest.
+//!
+//! [Trace Event Format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+
+use crate::report::{Result, SpanExporter};
+use crate::span::cycle::DefaultClock;
+use crate::span::span_id::SpanId;
+use crate::{Span, TraceId};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChromeTrace {
+    pub trace_events: Vec<TraceEvent>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TraceEvent {
+    pub name: String,
+    pub ph: &'static str,
+    /// Microseconds since the earliest span of the trace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ts: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dur: Option<f64>,
+    /// Scope of an instant event, `"t"` for the thread.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub s: Option<&'static str>,
+    pub pid: u32,
+    pub tid: u64,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, String>,
+}
+
+pub fn convert(spans: Vec<Span>) -> ChromeTrace {
+    let anchor = DefaultClock::anchor();
+    let pid = std::process::id();
+
+    let mut spans = spans;
+    spans.sort_by_key(|s| s.begin_cycle);
+
+    let tracks = assign_tracks(&spans);
+    let origin = spans
+        .first()
+        .map(|s| DefaultClock::cycle_to_realtime(s.begin_cycle, anchor).ns)
+        .unwrap_or_default();
+
+    // name each track after the span starting it
+    let mut trace_events = Vec::with_capacity(spans.len() * 2);
+    let mut named = HashSet::new();
+    for (span, tid) in spans.iter().zip(tracks.iter()) {
+        if named.insert(*tid) {
+            trace_events.push(TraceEvent {
+                name: "thread_name".to_owned(),
+                ph: "M",
+                ts: None,
+                dur: None,
+                s: None,
+                pid,
+                tid: *tid,
+                args: vec![("name".to_owned(), span.event.to_string())]
+                    .into_iter()
+                    .collect(),
+            });
+        }
+    }
+
+    for (span, tid) in spans.into_iter().zip(tracks) {
+        let begin_ns = DefaultClock::cycle_to_realtime(span.begin_cycle, anchor).ns;
+        let end_ns = DefaultClock::cycle_to_realtime(span.end_cycle, anchor).ns;
+        for log in span.logs {
+            let ns = DefaultClock::cycle_to_realtime(log.cycle, anchor).ns;
+            trace_events.push(TraceEvent {
+                name: log.event.into_owned(),
+                ph: "i",
+                ts: Some((ns - origin) as f64 / 1_000.0),
+                dur: None,
+                s: Some("t"),
+                pid,
+                tid,
+                args: log
+                    .properties
+                    .into_iter()
+                    .map(|(k, v)| (k.into_owned(), v.to_string()))
+                    .collect(),
+            });
+        }
+        trace_events.push(TraceEvent {
+            name: span.event.to_string(),
+            ph: "X",
+            ts: Some((begin_ns - origin) as f64 / 1_000.0),
+            dur: Some((end_ns - begin_ns) as f64 / 1_000.0),
+            s: None,
+            pid,
+            tid,
+            args: span
+                .properties
+                .into_iter()
+                .map(|(k, v)| (k.into_owned(), v.to_string()))
+                .collect(),
+        });
+    }
+
+    ChromeTrace { trace_events }
+}
+
+pub fn encode(spans: Vec<Span>) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&convert(spans))?)
+}
+
+/// Writes each exported trace to `{dir}/{trace_id}.json`.
+pub struct ChromeTraceWriter {
+    dir: PathBuf,
+}
+
+impl ChromeTraceWriter {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ChromeTraceWriter { dir: dir.into() }
+    }
+}
+
+impl SpanExporter for ChromeTraceWriter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        let path = self.dir.join(format!("{}.json", trace_id));
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, &convert(spans))?;
+        Ok(())
+    }
+}
+
+/// Returns the track of each span. Spans are expected to be sorted by `begin_cycle`.
+///
+/// A scope span starts a new track, and a local span shares the track of its parent unless
+/// it overlaps a sibling there, e.g. when a scope is entered by several threads at once.
+fn assign_tracks(spans: &[Span]) -> Vec<u64> {
+    let index_of: HashMap<SpanId, usize> =
+        spans.iter().enumerate().map(|(i, s)| (s.id, i)).collect();
+
+    // indexes of the spans open on each track, the innermost last
+    let mut open_spans: Vec<Vec<usize>> = vec![];
+    let mut tracks: Vec<usize> = Vec::with_capacity(spans.len());
+    for (i, span) in spans.iter().enumerate() {
+        let parent = index_of.get(&span.parent_id).copied().filter(|p| *p < i);
+        let track = match parent {
+            Some(parent) if !span._is_scope_span => {
+                let track = tracks[parent];
+                let open = &mut open_spans[track];
+                while let Some(&last) = open.last() {
+                    if spans[last].end_cycle > span.begin_cycle {
+                        break;
+                    }
+                    open.pop();
+                }
+                if open.last() == Some(&parent) && span.end_cycle <= spans[parent].end_cycle {
+                    Some(track)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        let track = track.unwrap_or_else(|| {
+            open_spans.push(vec![]);
+            open_spans.len() - 1
+        });
+        open_spans[track].push(i);
+        tracks.push(track);
+    }
+
+    tracks.into_iter().map(|t| t as u64 + 1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::spans_of;
+    use crate::{new_span, root_scope, spawn_scope, CollectOptions};
+    use serde_json::Value;
+    use std::sync::{Arc, Barrier};
+
+    #[test]
+    fn separate_tracks_for_scopes() {
+        let spans = spans_of(|| {
+            let _g = new_span("main").with_property(|| ("key", "value".into()));
+
+            let scope = spawn_scope("spawned");
+            std::thread::spawn(move || {
+                let _sg = scope.start_scope();
+                let _g = new_span("in thread");
+            })
+            .join()
+            .unwrap();
+        });
+
+        let json: Value = serde_json::from_slice(&encode(spans).unwrap()).unwrap();
+        let events = json["traceEvents"].as_array().unwrap();
+        let complete_event = |name: &str| {
+            events
+                .iter()
+                .find(|e| e["ph"] == "X" && e["name"] == name)
+                .unwrap()
+                .clone()
+        };
+
+        let root = complete_event("root");
+        let main = complete_event("main");
+        let spawned = complete_event("spawned");
+        let in_thread = complete_event("in thread");
+
+        assert_eq!(root["ts"], 0.0);
+        assert_eq!(root["tid"], main["tid"]);
+        assert_eq!(spawned["tid"], in_thread["tid"]);
+        assert_ne!(root["tid"], spawned["tid"]);
+        assert_eq!(main["args"]["key"], "value");
+
+        let track_names: Vec<_> = events
+            .iter()
+            .filter(|e| e["ph"] == "M")
+            .map(|e| e["args"]["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(track_names, vec!["root", "spawned"]);
+    }
+
+    #[test]
+    fn separate_tracks_for_threads() {
+        let (scope, collector) = root_scope("root");
+        let barrier = Arc::new(Barrier::new(2));
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let (scope, barrier) = (scope.clone(), barrier.clone());
+                std::thread::spawn(move || {
+                    let _sg = scope.start_scope();
+                    let _g = new_span("in thread");
+                    // both spans are open at once
+                    barrier.wait();
+                    let _g = new_span("nested");
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        drop(scope);
+        let spans = collector
+            .collect(CollectOptions::new().with_sync(true))
+            .spans;
+
+        let json: Value = serde_json::from_slice(&encode(spans).unwrap()).unwrap();
+        let tids_of = |name: &str| {
+            json["traceEvents"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|e| e["ph"] == "X" && e["name"] == name)
+                .map(|e| e["tid"].as_u64().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let in_thread = tids_of("in thread");
+        assert_ne!(in_thread[0], in_thread[1]);
+        let mut nested = tids_of("nested");
+        nested.sort();
+        let mut expected = in_thread;
+        expected.sort();
+        assert_eq!(nested, expected);
+    }
+}
@@ -0,0 +1,116 @@
+//! Aggregates traces into the folded stack format, one `root;child;grandchild <self time>`
+//! line per distinct stack, which [inferno] and [FlameGraph] turn into flame graphs.
+//!
+//! [inferno]: https://github.com/jonhoo/inferno
+//! [FlameGraph]: https://github.com/brendangregg/FlameGraph
+
+use crate::span::cycle::{Anchor, DefaultClock};
+use crate::span::tree::SpanTree;
+use crate::Span;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Self time in nanoseconds of each stack, summed over all added traces.
+#[derive(Clone, Debug, Default)]
+pub struct FoldedStacks {
+    stacks: BTreeMap<String, u64>,
+}
+
+impl FoldedStacks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the spans of one trace.
+    ///
+    /// The self time of a span is its duration minus the durations of its children. Children
+    /// running concurrently, e.g. under spawned scopes, may add up to more than the parent, in
+    /// which case the parent gets no self time.
+    pub fn add(&mut self, spans: &[Span]) {
+        let anchor = DefaultClock::anchor();
+        let tree = SpanTree::new(spans);
+
+        let mut pending: Vec<(usize, String)> = tree
+            .roots()
+            .iter()
+            .map(|&i| (i, frame(tree.span(i))))
+            .collect();
+        while let Some((index, stack)) = pending.pop() {
+            let children_ns: u64 = tree
+                .children(index)
+                .iter()
+                .map(|&c| duration_ns(tree.span(c), anchor))
+                .sum();
+            let self_ns = duration_ns(tree.span(index), anchor).saturating_sub(children_ns);
+
+            for &c in tree.children(index) {
+                pending.push((c, format!("{};{}", stack, frame(tree.span(c)))));
+            }
+            *self.stacks.entry(stack).or_insert(0) += self_ns;
+        }
+    }
+
+    /// Returns the stacks and their self time in nanoseconds, ordered by stack.
+    pub fn stacks(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.stacks.iter().map(|(s, ns)| (s.as_str(), *ns))
+    }
+
+    /// Writes one `stack self_time_ns` line per stack.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for (stack, ns) in self.stacks() {
+            writeln!(writer, "{} {}", stack, ns)?;
+        }
+        Ok(())
+    }
+}
+
+/// `;` separates frames and is replaced in span names.
+fn frame(span: &Span) -> String {
+    span.event.replace(';', ":")
+}
+
+fn duration_ns(span: &Span, anchor: Anchor) -> u64 {
+    let begin = DefaultClock::cycle_to_realtime(span.begin_cycle, anchor).ns;
+    let end = DefaultClock::cycle_to_realtime(span.end_cycle, anchor).ns;
+    end.saturating_sub(begin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_span;
+    use crate::testing::spans_of;
+
+    fn spans() -> Vec<Span> {
+        spans_of(|| {
+            for _ in 0..2 {
+                let _g = new_span("child");
+                let _g = new_span("grand;child");
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+        })
+    }
+
+    #[test]
+    fn fold() {
+        let anchor = DefaultClock::anchor();
+        let mut folded = FoldedStacks::new();
+        let mut total_ns = 0;
+        for _ in 0..3 {
+            let spans = spans();
+            total_ns += duration_ns(spans.iter().find(|s| s.is_root()).unwrap(), anchor);
+            folded.add(&spans);
+        }
+
+        let stacks: Vec<_> = folded.stacks().map(|(s, _)| s).collect();
+        assert_eq!(stacks, vec!["root", "root;child", "root;child;grand:child"]);
+        assert_eq!(folded.stacks().map(|(_, ns)| ns).sum::<u64>(), total_ns);
+        assert!(folded.stacks().last().unwrap().1 >= 6_000_000);
+
+        let mut out = vec![];
+        folded.write_to(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 3);
+        assert!(out.starts_with("root "));
+    }
+}
@@ -0,0 +1,198 @@
+//! A minimal HTTP/1.1 client, just enough to `POST` encoded spans to a collector, either
+//! blocking or, with the `tokio` feature, on tokio.
+
+use crate::report::Result;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(crate) fn post(url: &str, content_type: &str, body: &[u8]) -> Result<()> {
+    let (authority, path) = split_url(url)?;
+    let addr = authority
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("failed to resolve {}", authority))?;
+
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    stream.write_all(&request(&authority, path, content_type, body))?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    check_status(&response)
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) async fn post_async(url: &str, content_type: &str, body: &[u8]) -> Result<()> {
+    let (authority, path) = split_url(url)?;
+    let request = request(&authority, path, content_type, body);
+
+    let response = tokio::time::timeout(TIMEOUT, async {
+        let mut stream = tokio::net::TcpStream::connect(authority.as_str()).await?;
+        stream.write_all(&request).await?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok::<_, std::io::Error>(response)
+    })
+    .await
+    .map_err(|_| format!("timed out posting to {}", url))??;
+    check_status(&response)
+}
+
+fn request(authority: &str, path: &str, content_type: &str, body: &[u8]) -> Vec<u8> {
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        path,
+        authority,
+        content_type,
+        body.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(body);
+    request
+}
+
+/// Splits `http://host[:port][/path]` into `host:port` and `/path`.
+pub(crate) fn split_url(url: &str) -> Result<(String, &str)> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("unsupported url {}, only http:// is supported", url))?;
+
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(format!("missing host in url {}", url).into());
+    }
+
+    if authority.rfind(':') > authority.rfind(']') {
+        Ok((authority.to_owned(), path))
+    } else {
+        Ok((format!("{}:80", authority), path))
+    }
+}
+
+pub(crate) fn check_status(response: &[u8]) -> Result<()> {
+    let status_line = response
+        .split(|b| *b == b'\n')
+        .next()
+        .map(|l| String::from_utf8_lossy(l).trim_end().to_owned())
+        .unwrap_or_default();
+
+    match status_line.split(' ').nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(format!("unexpected http response: {:?}", status_line).into()),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod stand_in {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    pub(crate) struct Request {
+        pub path: String,
+        pub headers: Vec<(String, String)>,
+        pub body: Vec<u8>,
+    }
+
+    impl Request {
+        pub fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+        }
+    }
+
+    /// Accepts a single request on a local port and answers it with `200 OK`.
+    ///
+    /// Returns the base url of the server, e.g. `http://127.0.0.1:34567`.
+    pub(crate) fn serve_once() -> (String, JoinHandle<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut buf = Vec::new();
+            let header_end = loop {
+                let mut chunk = [0; 1024];
+                let n = stream.read(&mut chunk).unwrap();
+                assert_ne!(n, 0, "connection closed before headers were sent");
+                buf.extend_from_slice(&chunk[..n]);
+                if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break i;
+                }
+            };
+
+            let head = String::from_utf8(buf[..header_end].to_vec()).unwrap();
+            let mut lines = head.split("\r\n");
+            let path = lines.next().unwrap().split(' ').nth(1).unwrap().to_owned();
+            let headers: Vec<_> = lines
+                .filter_map(|l| {
+                    let i = l.find(':')?;
+                    Some((l[..i].trim().to_owned(), l[i + 1..].trim().to_owned()))
+                })
+                .collect();
+
+            let mut request = Request {
+                path,
+                headers,
+                body: buf[header_end + 4..].to_vec(),
+            };
+            let len: usize = request.header("Content-Length").unwrap().parse().unwrap();
+            while request.body.len() < len {
+                let mut chunk = [0; 4096];
+                let n = stream.read(&mut chunk).unwrap();
+                assert_ne!(n, 0, "connection closed before body was sent");
+                request.body.extend_from_slice(&chunk[..n]);
+            }
+
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            request
+        });
+
+        (url, handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            split_url("http://localhost:4318/v1/traces").unwrap(),
+            ("localhost:4318".to_owned(), "/v1/traces")
+        );
+        assert_eq!(
+            split_url("http://localhost").unwrap(),
+            ("localhost:80".to_owned(), "/")
+        );
+        assert_eq!(
+            split_url("http://[::1]/api").unwrap(),
+            ("[::1]:80".to_owned(), "/api")
+        );
+        assert!(split_url("https://localhost").is_err());
+    }
+
+    #[test]
+    fn status() {
+        assert!(check_status(b"HTTP/1.1 202 Accepted\r\n\r\n").is_ok());
+        assert!(check_status(b"HTTP/1.1 404 Not Found\r\n\r\n").is_err());
+        assert!(check_status(b"").is_err());
+    }
+}
@@ -0,0 +1,485 @@
+//! Decodes the `emitBatch` messages `Reporter` sends to the agent, i.e. [agent.thrift] in the
+//! Thrift compact protocol, back into [jaeger.thrift] structs.
+//!
+//! Unknown fields are skipped, so payloads of other Jaeger clients can be decoded as well.
+//!
+//! [agent.thrift]: https://github.com/uber/jaeger-idl/blob/master/thrift/agent.thrift
+//! [jaeger.thrift]: https://github.com/uber/jaeger-idl/blob/master/thrift/jaeger.thrift
+
+use crate::report::Result;
+use rustracing_jaeger::thrift::jaeger::{
+    Batch, Log as JaegerLog, Process, Span as JaegerSpan, SpanRef, SpanRefKind, Tag,
+};
+use std::convert::TryFrom;
+
+const PROTOCOL_ID: u8 = 0x82;
+const VERSION: u8 = 1;
+const MESSAGE_TYPE_ONEWAY: u8 = 4;
+
+const TYPE_BOOL_TRUE: u8 = 1;
+const TYPE_BOOL_FALSE: u8 = 2;
+const TYPE_BYTE: u8 = 3;
+const TYPE_I16: u8 = 4;
+const TYPE_I32: u8 = 5;
+const TYPE_I64: u8 = 6;
+const TYPE_DOUBLE: u8 = 7;
+const TYPE_BINARY: u8 = 8;
+const TYPE_LIST: u8 = 9;
+const TYPE_SET: u8 = 10;
+const TYPE_MAP: u8 = 11;
+const TYPE_STRUCT: u8 = 12;
+
+/// Deeper structs and collections are rejected rather than risking a stack overflow.
+const MAX_DEPTH: usize = 64;
+
+/// Decodes one UDP packet sent to the agent.
+pub fn decode_emit_batch(bytes: &[u8]) -> Result<Batch> {
+    let r = &mut Reader { bytes, depth: 0 };
+
+    if r.u8()? != PROTOCOL_ID {
+        return Err("not a thrift compact message".into());
+    }
+    let version_and_type = r.u8()?;
+    if version_and_type & 0x1f != VERSION || version_and_type >> 5 != MESSAGE_TYPE_ONEWAY {
+        return Err(format!("unexpected message header {:#x}", version_and_type).into());
+    }
+    let _seq_id = r.varint()?;
+    let name = r.string()?;
+    if name != "emitBatch" {
+        return Err(format!("unexpected method {:?}", name).into());
+    }
+
+    let mut batch = None;
+    r.read_struct(|r, id, ty| match (id, ty) {
+        (1, TYPE_STRUCT) => {
+            batch = Some(read_batch(r)?);
+            Ok(())
+        }
+        _ => r.skip(ty),
+    })?;
+    batch.ok_or_else(|| "missing batch".into())
+}
+
+fn read_batch(r: &mut Reader) -> Result<Batch> {
+    let mut process = None;
+    let mut spans = vec![];
+    r.read_struct(|r, id, ty| match (id, ty) {
+        (1, TYPE_STRUCT) => {
+            process = Some(read_process(r)?);
+            Ok(())
+        }
+        (2, TYPE_LIST) => {
+            spans = r.read_list(TYPE_STRUCT, read_span)?;
+            Ok(())
+        }
+        _ => r.skip(ty),
+    })?;
+
+    Ok(Batch {
+        process: process.ok_or("missing process")?,
+        spans,
+    })
+}
+
+fn read_process(r: &mut Reader) -> Result<Process> {
+    let mut process = Process {
+        service_name: String::new(),
+        tags: vec![],
+    };
+    r.read_struct(|r, id, ty| {
+        match (id, ty) {
+            (1, TYPE_BINARY) => process.service_name = r.string()?,
+            (2, TYPE_LIST) => process.tags = r.read_list(TYPE_STRUCT, read_tag)?,
+            _ => r.skip(ty)?,
+        }
+        Ok(())
+    })?;
+    Ok(process)
+}
+
+fn read_span(r: &mut Reader) -> Result<JaegerSpan> {
+    let mut span = JaegerSpan {
+        trace_id_low: 0,
+        trace_id_high: 0,
+        span_id: 0,
+        parent_span_id: 0,
+        operation_name: String::new(),
+        references: vec![],
+        flags: 0,
+        start_time: 0,
+        duration: 0,
+        tags: vec![],
+        logs: vec![],
+    };
+    r.read_struct(|r, id, ty| {
+        match (id, ty) {
+            (1, TYPE_I64) => span.trace_id_low = r.i64()?,
+            (2, TYPE_I64) => span.trace_id_high = r.i64()?,
+            (3, TYPE_I64) => span.span_id = r.i64()?,
+            (4, TYPE_I64) => span.parent_span_id = r.i64()?,
+            (5, TYPE_BINARY) => span.operation_name = r.string()?,
+            (6, TYPE_LIST) => span.references = r.read_list(TYPE_STRUCT, read_span_ref)?,
+            (7, TYPE_I32) => span.flags = r.i32()?,
+            (8, TYPE_I64) => span.start_time = r.i64()?,
+            (9, TYPE_I64) => span.duration = r.i64()?,
+            (10, TYPE_LIST) => span.tags = r.read_list(TYPE_STRUCT, read_tag)?,
+            (11, TYPE_LIST) => span.logs = r.read_list(TYPE_STRUCT, read_log)?,
+            _ => r.skip(ty)?,
+        }
+        Ok(())
+    })?;
+    Ok(span)
+}
+
+fn read_span_ref(r: &mut Reader) -> Result<SpanRef> {
+    let mut span_ref = SpanRef {
+        kind: SpanRefKind::ChildOf,
+        trace_id_low: 0,
+        trace_id_high: 0,
+        span_id: 0,
+    };
+    r.read_struct(|r, id, ty| {
+        match (id, ty) {
+            (1, TYPE_I32) => {
+                span_ref.kind = match r.i32()? {
+                    0 => SpanRefKind::ChildOf,
+                    1 => SpanRefKind::FollowsFrom,
+                    kind => return Err(format!("unknown span ref kind {}", kind).into()),
+                }
+            }
+            (2, TYPE_I64) => span_ref.trace_id_low = r.i64()?,
+            (3, TYPE_I64) => span_ref.trace_id_high = r.i64()?,
+            (4, TYPE_I64) => span_ref.span_id = r.i64()?,
+            _ => r.skip(ty)?,
+        }
+        Ok(())
+    })?;
+    Ok(span_ref)
+}
+
+fn read_log(r: &mut Reader) -> Result<JaegerLog> {
+    let mut log = JaegerLog {
+        timestamp: 0,
+        fields: vec![],
+    };
+    r.read_struct(|r, id, ty| {
+        match (id, ty) {
+            (1, TYPE_I64) => log.timestamp = r.i64()?,
+            (2, TYPE_LIST) => log.fields = r.read_list(TYPE_STRUCT, read_tag)?,
+            _ => r.skip(ty)?,
+        }
+        Ok(())
+    })?;
+    Ok(log)
+}
+
+fn read_tag(r: &mut Reader) -> Result<Tag> {
+    let mut key = String::new();
+    let mut kind = None;
+    let mut v_str = None;
+    let mut v_double = None;
+    let mut v_bool = None;
+    let mut v_long = None;
+    let mut v_binary = None;
+    r.read_struct(|r, id, ty| {
+        match (id, ty) {
+            (1, TYPE_BINARY) => key = r.string()?,
+            (2, TYPE_I32) => kind = Some(r.i32()?),
+            (3, TYPE_BINARY) => v_str = Some(r.string()?),
+            (4, TYPE_DOUBLE) => v_double = Some(r.double()?),
+            (5, TYPE_BOOL_TRUE) => v_bool = Some(true),
+            (5, TYPE_BOOL_FALSE) => v_bool = Some(false),
+            (6, TYPE_I64) => v_long = Some(r.i64()?),
+            (7, TYPE_BINARY) => v_binary = Some(r.binary()?),
+            _ => r.skip(ty)?,
+        }
+        Ok(())
+    })?;
+
+    let missing = || format!("missing value of tag {:?}", key);
+    Ok(match kind {
+        Some(0) => Tag::String {
+            value: v_str.ok_or_else(missing)?,
+            key,
+        },
+        Some(1) => Tag::Double {
+            value: v_double.ok_or_else(missing)?,
+            key,
+        },
+        Some(2) => Tag::Bool {
+            value: v_bool.ok_or_else(missing)?,
+            key,
+        },
+        Some(3) => Tag::Long {
+            value: v_long.ok_or_else(missing)?,
+            key,
+        },
+        Some(4) => Tag::Binary {
+            value: v_binary.ok_or_else(missing)?,
+            key,
+        },
+        _ => return Err(format!("unknown type {:?} of tag {:?}", kind, key).into()),
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Calls `f` with the id and type of each field. `f` must consume the value of the field.
+    ///
+    /// Boolean fields carry their value in the type, `TYPE_BOOL_TRUE` or `TYPE_BOOL_FALSE`.
+    fn read_struct(&mut self, mut f: impl FnMut(&mut Self, i16, u8) -> Result<()>) -> Result<()> {
+        self.nested(|r| {
+            let mut last_id = 0i16;
+            loop {
+                let header = r.u8()?;
+                if header == 0 {
+                    return Ok(());
+                }
+
+                let ty = header & 0x0f;
+                let delta = header >> 4;
+                let id = if delta == 0 {
+                    r.i16()?
+                } else {
+                    last_id
+                        .checked_add(delta as i16)
+                        .ok_or("field id out of range")?
+                };
+                last_id = id;
+                f(r, id, ty)?;
+            }
+        })
+    }
+
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth == MAX_DEPTH {
+            return Err("nested too deeply".into());
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn read_list<T>(
+        &mut self,
+        elem_type: u8,
+        mut f: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let (ty, len) = self.list_header()?;
+        if len > 0 && ty != elem_type {
+            return Err(format!("expect list of type {}, got {}", elem_type, ty).into());
+        }
+        (0..len).map(|_| f(self)).collect()
+    }
+
+    fn list_header(&mut self) -> Result<(u8, usize)> {
+        let header = self.u8()?;
+        let len = match header >> 4 {
+            0x0f => self.varint()? as usize,
+            len => len as usize,
+        };
+        // every element takes at least a byte
+        if len > self.bytes.len() {
+            return Err("list is longer than the payload".into());
+        }
+        Ok((header & 0x0f, len))
+    }
+
+    fn skip(&mut self, ty: u8) -> Result<()> {
+        match ty {
+            TYPE_BOOL_TRUE | TYPE_BOOL_FALSE => {}
+            TYPE_BYTE => {
+                self.u8()?;
+            }
+            TYPE_I16 | TYPE_I32 | TYPE_I64 => {
+                self.varint()?;
+            }
+            TYPE_DOUBLE => {
+                self.take(8)?;
+            }
+            TYPE_BINARY => {
+                self.binary()?;
+            }
+            TYPE_LIST | TYPE_SET => self.nested(|r| {
+                let (elem_type, len) = r.list_header()?;
+                (0..len).try_for_each(|_| r.skip_element(elem_type))
+            })?,
+            TYPE_MAP => self.nested(|r| {
+                let len = r.varint()?;
+                if len > 0 {
+                    let types = r.u8()?;
+                    for _ in 0..len {
+                        r.skip_element(types >> 4)?;
+                        r.skip_element(types & 0x0f)?;
+                    }
+                }
+                Ok(())
+            })?,
+            TYPE_STRUCT => self.read_struct(|r, _, ty| r.skip(ty))?,
+            _ => return Err(format!("unknown type {}", ty).into()),
+        }
+        Ok(())
+    }
+
+    /// Booleans in collections take a byte, unlike boolean fields.
+    fn skip_element(&mut self, ty: u8) -> Result<()> {
+        match ty {
+            TYPE_BOOL_TRUE | TYPE_BOOL_FALSE => self.u8().map(drop),
+            _ => self.skip(ty),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err("unexpected end of payload".into());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("varint is too long".into())
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        let n = self.varint()?;
+        Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        let n = self.i64()?;
+        i32::try_from(n).map_err(|_| format!("{} is out of range for i32", n).into())
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        let n = self.i64()?;
+        i16::try_from(n).map_err(|_| format!("{} is out of range for i16", n).into())
+    }
+
+    fn double(&mut self) -> Result<f64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    fn binary(&mut self) -> Result<Vec<u8>> {
+        let len = self.varint()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn string(&mut self) -> Result<String> {
+        Ok(String::from_utf8(self.binary()?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::jaeger::Reporter;
+    use crate::span::cycle::DefaultClock;
+    use crate::testing::trace_of;
+    use crate::{new_span, spawn_scope, TraceId};
+
+    #[test]
+    fn round_trip() {
+        let trace = trace_of(|| {
+            let g = new_span("child").with_properties(|| {
+                vec![
+                    ("string", "value".into()),
+                    ("long", (-42).into()),
+                    ("double", 0.5.into()),
+                    ("true", true.into()),
+                    ("false", false.into()),
+                    ("binary", vec![1u8, 2, 3].into()),
+                ]
+            });
+            g.log_event_with_properties("retry", || vec![("attempt", 2.into())]);
+            for i in 0..20 {
+                let _g = new_span("many").with_property(|| ("index", i.into()));
+            }
+
+            let scope = spawn_scope("spawned");
+            std::thread::spawn(move || {
+                let _sg = scope.start_scope();
+            })
+            .join()
+            .unwrap();
+        });
+
+        let trace_id = trace.trace_id;
+        let spans: Vec<_> = trace
+            .spans
+            .into_iter()
+            .map(|s| Reporter::convert(s, trace_id, DefaultClock::anchor()))
+            .collect();
+        let process = crate::report::jaeger::Process::new("decode")
+            .with_tag("hostname", "host-1")
+            .with_tag("pid", 42)
+            .to_thrift();
+        let bytes = Reporter::encode_batch(process.clone(), spans.clone()).unwrap();
+
+        let batch = decode_emit_batch(&bytes).unwrap();
+        assert_eq!(format!("{:?}", batch.process), format!("{:?}", process));
+        assert_eq!(format!("{:?}", batch.spans), format!("{:?}", spans));
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = Reporter::encode("decode".to_owned(), TraceId::new(1), vec![]).unwrap();
+        assert!(decode_emit_batch(&bytes).is_ok());
+        for len in 0..bytes.len() {
+            assert!(decode_emit_batch(&bytes[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        let mut header = vec![PROTOCOL_ID, MESSAGE_TYPE_ONEWAY << 5 | VERSION, 0, 9];
+        header.extend_from_slice(b"emitBatch");
+
+        // field 32767 of type i32, followed by the next field
+        let mut bytes = header.clone();
+        bytes.extend_from_slice(&[TYPE_I32, 0xfe, 0xff, 0x03, 0, 0x10 | TYPE_I32, 0]);
+        assert!(decode_emit_batch(&bytes).is_err());
+
+        let mut bytes = header;
+        bytes.resize(bytes.len() + 65_000, 0x10 | TYPE_STRUCT);
+        assert!(decode_emit_batch(&bytes).is_err());
+    }
+
+    #[test]
+    fn out_of_range() {
+        // zigzag varints of 2^15 and 2^31
+        let mut r = Reader {
+            bytes: &[0x80, 0x80, 0x04, 0x80, 0x80, 0x80, 0x80, 0x10],
+            depth: 0,
+        };
+        assert!(r.i16().is_err());
+        assert!(r.i32().is_err());
+
+        let mut r = Reader {
+            bytes: &[0xfe, 0xff, 0x03, 0xfe, 0xff, 0xff, 0xff, 0x0f],
+            depth: 0,
+        };
+        assert_eq!(r.i16().unwrap(), i16::MAX);
+        assert_eq!(r.i32().unwrap(), i32::MAX);
+    }
+}
@@ -0,0 +1,755 @@
+pub mod decode;
+
+use crate::report::{http, Result, SpanExporter};
+#[cfg(feature = "tokio")]
+use crate::report::{AsyncSpanExporter, BoxFuture};
+use crate::span::cycle::{Anchor, DefaultClock};
+use crate::{PropertyValue, RefKind, Span, TraceId};
+use rustracing_jaeger::thrift::agent::EmitBatchNotification;
+use rustracing_jaeger::thrift::jaeger::{
+    Batch, Log as JaegerLog, Process as JaegerProcess, Span as JaegerSpan, SpanRef, SpanRefKind,
+    Tag,
+};
+use std::net::{SocketAddr, UdpSocket};
+use thrift_codec::data::Struct;
+use thrift_codec::message::Message;
+use thrift_codec::{BinaryEncode, CompactEncode};
+
+pub const DEFAULT_COLLECTOR_ENDPOINT: &str = "http://127.0.0.1:14268";
+
+/// The largest payload an agent accepts in one datagram, leaving room for IP and UDP headers.
+pub const DEFAULT_MAX_PACKET_SIZE: usize = 65_000;
+
+/// The process reported with every batch: the name of the service, and tags telling its
+/// instances apart, e.g. `hostname`, `ip`, `pid` or `version`.
+#[derive(Clone, Debug)]
+pub struct Process {
+    service_name: String,
+    tags: Vec<(String, PropertyValue)>,
+}
+
+impl Process {
+    pub fn new(service_name: impl Into<String>) -> Self {
+        Process {
+            service_name: service_name.into(),
+            tags: vec![],
+        }
+    }
+
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<PropertyValue>) -> Self {
+        self.tags.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn with_tags<K: Into<String>, V: Into<PropertyValue>, I: IntoIterator<Item = (K, V)>>(
+        self,
+        tags: I,
+    ) -> Self {
+        tags.into_iter()
+            .fold(self, |p, (key, value)| p.with_tag(key, value))
+    }
+
+    fn to_thrift(&self) -> JaegerProcess {
+        JaegerProcess {
+            service_name: self.service_name.clone(),
+            tags: self
+                .tags
+                .iter()
+                .map(|(key, value)| Reporter::tag(key.as_str(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl From<String> for Process {
+    fn from(service_name: String) -> Self {
+        Process::new(service_name)
+    }
+}
+
+impl From<&str> for Process {
+    fn from(service_name: &str) -> Self {
+        Process::new(service_name)
+    }
+}
+
+pub struct Reporter {
+    agent: SocketAddr,
+    process: JaegerProcess,
+    max_packet_size: usize,
+}
+
+impl Reporter {
+    /// `process` is a [`Process`], or just the name of the service.
+    pub fn new(agent: SocketAddr, process: impl Into<Process>) -> Self {
+        Reporter {
+            agent,
+            process: process.into().to_thrift(),
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+        }
+    }
+
+    /// Sets the maximum size of a datagram sent to the agent. Spans of a trace that don't fit
+    /// into one datagram are split across several batches, and a span too large for a datagram
+    /// of its own is dropped.
+    pub fn with_max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    pub fn encode(
+        process: impl Into<Process>,
+        trace_id: TraceId,
+        spans: Vec<Span>,
+    ) -> Result<Vec<u8>> {
+        let anchor = DefaultClock::anchor();
+        let spans = spans
+            .into_iter()
+            .map(|s| Self::convert(s, trace_id, anchor))
+            .collect();
+        Self::encode_batch(process.into().to_thrift(), spans)
+    }
+
+    /// Encodes spans into one or more `EmitBatchNotification`s, each of which is no larger than
+    /// `max_packet_size` bytes.
+    ///
+    /// Spans too large to fit into a packet on their own are left out, as the agent could not
+    /// receive them.
+    pub fn encode_batches(
+        process: impl Into<Process>,
+        trace_id: TraceId,
+        spans: Vec<Span>,
+        max_packet_size: usize,
+    ) -> Result<Vec<Vec<u8>>> {
+        let anchor = DefaultClock::anchor();
+        let process = process.into().to_thrift();
+        let spans = spans
+            .into_iter()
+            .map(|s| Self::convert(s, trace_id, anchor));
+
+        Self::split(&process, spans, max_packet_size)?
+            .0
+            .into_iter()
+            .map(|spans| Self::encode_batch(process.clone(), spans))
+            .collect()
+    }
+
+    pub fn report(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report_traces(vec![(trace_id, spans)])
+    }
+
+    /// Reports spans of several traces, packing them into as few datagrams as possible.
+    ///
+    /// All datagrams are sent even if some fail, or spans too large for a datagram are dropped,
+    /// in which case the first error is returned afterwards.
+    pub fn report_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        let udp = UdpSocket::bind(self.local_addr()?)?;
+        let (packets, oversized) = self.packets(traces)?;
+        let mut result = Ok(());
+        for bytes in packets {
+            if let Err(e) = udp.send_to(&bytes, self.agent) {
+                result = result.and(Err(e.into()));
+            }
+        }
+
+        result.and(self.check_oversized(oversized))
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn report_async(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report_traces_async(vec![(trace_id, spans)]).await
+    }
+
+    /// Like [`Reporter::report_traces`], but sends the datagrams through a tokio socket.
+    #[cfg(feature = "tokio")]
+    pub async fn report_traces_async(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        let mut udp = tokio::net::UdpSocket::bind(self.local_addr()?).await?;
+        let (packets, oversized) = self.packets(traces)?;
+        let mut result = Ok(());
+        for bytes in packets {
+            if let Err(e) = udp.send_to(&bytes, self.agent).await {
+                result = result.and(Err(e.into()));
+            }
+        }
+
+        result.and(self.check_oversized(oversized))
+    }
+}
+
+impl Reporter {
+    fn local_addr(&self) -> Result<SocketAddr> {
+        let local_addr = if self.agent.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        Ok(local_addr.parse()?)
+    }
+
+    /// Returns the datagrams to send, and the number of spans too large to send.
+    fn packets(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<(Vec<Vec<u8>>, usize)> {
+        let spans = Self::convert_traces(traces, DefaultClock::anchor());
+        let (batches, oversized) = Self::split(&self.process, spans, self.max_packet_size)?;
+        let packets = batches
+            .into_iter()
+            .map(|batch| Self::encode_batch(self.process.clone(), batch))
+            .collect::<Result<_>>()?;
+        Ok((packets, oversized))
+    }
+
+    fn check_oversized(&self, oversized: usize) -> Result<()> {
+        if oversized > 0 {
+            return Err(format!(
+                "dropped {} spans larger than the max packet size of {} bytes",
+                oversized, self.max_packet_size
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn convert(s: Span, trace_id: TraceId, anchor: Anchor) -> JaegerSpan {
+        let begin_cycles = DefaultClock::cycle_to_realtime(s.begin_cycle, anchor);
+        let end_time = DefaultClock::cycle_to_realtime(s.end_cycle, anchor);
+        JaegerSpan {
+            trace_id_low: trace_id.low() as i64,
+            trace_id_high: trace_id.high() as i64,
+            span_id: s.id.0 as i64,
+            parent_span_id: s.parent_id.0 as i64,
+            operation_name: s.event.to_string(),
+            references: if s.is_root() {
+                vec![]
+            } else {
+                vec![SpanRef {
+                    kind: match s.ref_kind {
+                        RefKind::ChildOf => SpanRefKind::ChildOf,
+                        RefKind::FollowsFrom => SpanRefKind::FollowsFrom,
+                    },
+                    trace_id_low: trace_id.low() as i64,
+                    trace_id_high: trace_id.high() as i64,
+                    span_id: s.parent_id.0 as i64,
+                }]
+            },
+            flags: 1,
+            start_time: (begin_cycles.ns / 1_000) as i64,
+            duration: ((end_time.ns - begin_cycles.ns) / 1_000) as i64,
+            tags: s
+                .properties
+                .into_iter()
+                .map(|(key, value)| Self::tag(key, value))
+                .collect(),
+            logs: s
+                .logs
+                .into_iter()
+                .map(|log| JaegerLog {
+                    timestamp: (DefaultClock::cycle_to_realtime(log.cycle, anchor).ns / 1_000)
+                        as i64,
+                    fields: std::iter::once(Self::tag("event", log.event.into()))
+                        .chain(
+                            log.properties
+                                .into_iter()
+                                .map(|(key, value)| Self::tag(key, value)),
+                        )
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    fn tag(key: impl Into<String>, value: PropertyValue) -> Tag {
+        let key = key.into();
+        match value {
+            PropertyValue::String(value) => Tag::String { key, value },
+            PropertyValue::I64(value) => Tag::Long { key, value },
+            PropertyValue::F64(value) => Tag::Double { key, value },
+            PropertyValue::Bool(value) => Tag::Bool { key, value },
+            PropertyValue::Bytes(value) => Tag::Binary { key, value },
+        }
+    }
+
+    fn convert_traces(
+        traces: Vec<(TraceId, Vec<Span>)>,
+        anchor: Anchor,
+    ) -> impl Iterator<Item = JaegerSpan> {
+        traces.into_iter().flat_map(move |(trace_id, spans)| {
+            spans
+                .into_iter()
+                .map(move |s| Self::convert(s, trace_id, anchor))
+        })
+    }
+
+    /// Returns the batches, and the number of spans left out as they exceed a packet alone.
+    fn split(
+        process: &JaegerProcess,
+        spans: impl Iterator<Item = JaegerSpan>,
+        max_packet_size: usize,
+    ) -> Result<(Vec<Vec<JaegerSpan>>, usize)> {
+        // the list header of spans takes at most 6 bytes in compact protocol
+        let overhead = Self::encode_batch(process.clone(), vec![])?.len() + 6;
+        let budget = max_packet_size.saturating_sub(overhead);
+
+        let mut batches = vec![];
+        let mut batch = vec![];
+        let mut batch_size = 0;
+        let mut buf = vec![];
+        let mut oversized = 0;
+        for span in spans {
+            buf.clear();
+            Struct::from(span.clone()).compact_encode(&mut buf)?;
+            let span_size = buf.len();
+
+            if span_size > budget {
+                oversized += 1;
+                continue;
+            }
+            if !batch.is_empty() && batch_size + span_size > budget {
+                batches.push(std::mem::take(&mut batch));
+                batch_size = 0;
+            }
+            batch.push(span);
+            batch_size += span_size;
+        }
+        if !batch.is_empty() {
+            batches.push(batch);
+        }
+
+        Ok((batches, oversized))
+    }
+
+    fn encode_batch(process: JaegerProcess, spans: Vec<JaegerSpan>) -> Result<Vec<u8>> {
+        let bn = EmitBatchNotification {
+            batch: Batch { process, spans },
+        };
+
+        let mut bytes = Vec::new();
+        let msg = Message::from(bn);
+        msg.compact_encode(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl SpanExporter for Reporter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report(trace_id, spans)
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        self.report_traces(traces)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncSpanExporter for Reporter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.report_async(trace_id, spans))
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.report_traces_async(traces))
+    }
+}
+
+/// Posts batches straight to a Jaeger collector's `/api/traces` endpoint in Thrift binary
+/// encoding, for environments where no agent runs alongside the service.
+pub struct HttpReporter {
+    endpoint: String,
+    process: JaegerProcess,
+}
+
+impl HttpReporter {
+    /// `endpoint` is the base url of the collector, e.g. [`DEFAULT_COLLECTOR_ENDPOINT`], and
+    /// `process` a [`Process`], or just the name of the service.
+    pub fn new(endpoint: impl Into<String>, process: impl Into<Process>) -> Self {
+        HttpReporter {
+            endpoint: endpoint.into(),
+            process: process.into().to_thrift(),
+        }
+    }
+
+    pub fn encode(
+        process: impl Into<Process>,
+        trace_id: TraceId,
+        spans: Vec<Span>,
+    ) -> Result<Vec<u8>> {
+        Self::encode_traces(process, vec![(trace_id, spans)])
+    }
+
+    pub fn encode_traces(
+        process: impl Into<Process>,
+        traces: Vec<(TraceId, Vec<Span>)>,
+    ) -> Result<Vec<u8>> {
+        Self::encode_batch(process.into().to_thrift(), traces)
+    }
+
+    pub fn report(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report_traces(vec![(trace_id, spans)])
+    }
+
+    pub fn report_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        let bytes = Self::encode_batch(self.process.clone(), traces)?;
+        let url = format!("{}/api/traces", self.endpoint.trim_end_matches('/'));
+        http::post(&url, "application/x-thrift", &bytes)
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn report_async(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report_traces_async(vec![(trace_id, spans)]).await
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn report_traces_async(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        let bytes = Self::encode_batch(self.process.clone(), traces)?;
+        let url = format!("{}/api/traces", self.endpoint.trim_end_matches('/'));
+        http::post_async(&url, "application/x-thrift", &bytes).await
+    }
+}
+
+impl HttpReporter {
+    fn encode_batch(process: JaegerProcess, traces: Vec<(TraceId, Vec<Span>)>) -> Result<Vec<u8>> {
+        let batch = Batch {
+            process,
+            spans: Reporter::convert_traces(traces, DefaultClock::anchor()).collect(),
+        };
+
+        let mut bytes = Vec::new();
+        Struct::from(batch).binary_encode(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl SpanExporter for HttpReporter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report(trace_id, spans)
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        self.report_traces(traces)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncSpanExporter for HttpReporter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.report_async(trace_id, spans))
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.report_traces_async(traces))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::http::stand_in;
+    use crate::testing::{spans_of, trace_of};
+    use crate::{new_span, spawn_scope, spawn_scope_with_ref_kind, Scope};
+    use std::io::ErrorKind;
+    use std::time::Duration;
+    use thrift_codec::data::Data;
+    use thrift_codec::BinaryDecode;
+
+    fn many_spans(count: usize) -> Vec<Span> {
+        spans_of(|| {
+            for i in 0..count {
+                let _g = new_span("span").with_property(|| ("index", i.into()));
+            }
+        })
+    }
+
+    fn converted(trace: crate::Trace) -> Vec<JaegerSpan> {
+        let (trace_id, anchor) = (trace.trace_id, DefaultClock::anchor());
+        trace
+            .spans
+            .into_iter()
+            .map(|s| Reporter::convert(s, trace_id, anchor))
+            .collect()
+    }
+
+    #[test]
+    fn split_into_batches() {
+        let anchor = DefaultClock::anchor();
+        let process = Process::new("split").to_thrift();
+        let trace_id = TraceId::new(0x0123_4567_89ab_cdef_fedc_ba98_7654_3210);
+        let spans: Vec<_> = many_spans(1000)
+            .into_iter()
+            .map(|s| Reporter::convert(s, trace_id, anchor))
+            .collect();
+        assert_eq!(spans[0].trace_id_high, 0x0123_4567_89ab_cdef);
+        assert_eq!(spans[0].trace_id_low, 0xfedc_ba98_7654_3210u64 as i64);
+
+        let (batches, oversized) =
+            Reporter::split(&process, spans.clone().into_iter(), 4096).unwrap();
+        assert_eq!(oversized, 0);
+        assert!(batches.len() > 1);
+        assert_eq!(batches.iter().map(|b| b.len()).sum::<usize>(), 1001);
+        for batch in batches {
+            let bytes = Reporter::encode_batch(process.clone(), batch).unwrap();
+            assert!(bytes.len() <= 4096);
+        }
+
+        // leave room for the reserved list header
+        let whole = Reporter::encode_batch(process.clone(), spans.clone()).unwrap();
+        let (batches, _) = Reporter::split(&process, spans.into_iter(), whole.len() + 6).unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].len(), 1001);
+    }
+
+    #[test]
+    fn references() {
+        let spans = converted(trace_of(|| {
+            let _g = new_span("child");
+            let _g = new_span("follower").with_ref_kind(RefKind::FollowsFrom);
+
+            let run_in_thread = |scope: Scope| {
+                std::thread::spawn(move || {
+                    let _sg = scope.start_scope();
+                })
+                .join()
+                .unwrap()
+            };
+            run_in_thread(spawn_scope("spawned"));
+            run_in_thread(spawn_scope_with_ref_kind("joined", RefKind::ChildOf));
+        }));
+        assert_eq!(spans.len(), 5);
+
+        let kind_of = |name: &str| {
+            let span = spans.iter().find(|s| s.operation_name == name).unwrap();
+            span.references.iter().map(|r| r.kind).collect::<Vec<_>>()
+        };
+        assert_eq!(kind_of("root"), vec![]);
+        assert_eq!(kind_of("child"), vec![SpanRefKind::ChildOf]);
+        assert_eq!(kind_of("follower"), vec![SpanRefKind::FollowsFrom]);
+        assert_eq!(kind_of("spawned"), vec![SpanRefKind::FollowsFrom]);
+        assert_eq!(kind_of("joined"), vec![SpanRefKind::ChildOf]);
+    }
+
+    #[test]
+    fn typed_tags() {
+        let spans = converted(trace_of(|| {
+            let _g = new_span("child").with_properties(|| {
+                vec![
+                    ("string", "value".into()),
+                    ("long", 42.into()),
+                    ("double", 0.5.into()),
+                    ("bool", true.into()),
+                    ("binary", vec![1u8, 2, 3].into()),
+                ]
+            });
+        }));
+        let child = spans.iter().find(|s| s.operation_name == "child").unwrap();
+        assert_eq!(
+            child.tags,
+            vec![
+                Tag::String {
+                    key: "string".to_owned(),
+                    value: "value".to_owned()
+                },
+                Tag::Long {
+                    key: "long".to_owned(),
+                    value: 42
+                },
+                Tag::Double {
+                    key: "double".to_owned(),
+                    value: 0.5
+                },
+                Tag::Bool {
+                    key: "bool".to_owned(),
+                    value: true
+                },
+                Tag::Binary {
+                    key: "binary".to_owned(),
+                    value: vec![1, 2, 3]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn logs() {
+        let spans = converted(trace_of(|| {
+            // no local span to log to yet
+            crate::log_event("ignored");
+
+            let g = new_span("child");
+            g.log_event_with_properties("retry", || vec![("attempt", 2.into())]);
+            {
+                let _g = new_span("grandchild");
+                crate::log_event("cache miss");
+            }
+            crate::log_event("done");
+        }));
+        let logs_of = |name: &str| {
+            let span = spans.iter().find(|s| s.operation_name == name).unwrap();
+            span.logs.clone()
+        };
+
+        assert!(logs_of("root").is_empty());
+        let logs = logs_of("child");
+        assert_eq!(logs.len(), 2);
+        assert!(logs[0].timestamp <= logs[1].timestamp);
+        assert_eq!(
+            logs[0].fields,
+            vec![
+                Tag::String {
+                    key: "event".to_owned(),
+                    value: "retry".to_owned()
+                },
+                Tag::Long {
+                    key: "attempt".to_owned(),
+                    value: 2
+                }
+            ]
+        );
+        assert_eq!(
+            logs[1].fields,
+            vec![Tag::String {
+                key: "event".to_owned(),
+                value: "done".to_owned()
+            }]
+        );
+        assert_eq!(logs_of("grandchild").len(), 1);
+    }
+
+    #[test]
+    fn report_large_trace() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+
+        let reporter = Reporter::new(agent.local_addr().unwrap(), "split");
+        reporter
+            .report(TraceId::new(42), many_spans(10_000))
+            .unwrap();
+
+        let mut packets = 0;
+        let mut buf = vec![0; 65_536];
+        loop {
+            match agent.recv(&mut buf) {
+                Ok(len) => {
+                    assert!(len <= DEFAULT_MAX_PACKET_SIZE);
+                    let batch = decode::decode_emit_batch(&buf[..len]).unwrap();
+                    assert_eq!(batch.process.service_name, "split");
+                    assert!(batch.spans.iter().all(|s| s.trace_id_low == 42));
+                    packets += 1;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                    break
+                }
+                Err(e) => panic!("{}", e),
+            }
+        }
+        assert!(packets > 1);
+    }
+
+    #[test]
+    fn report_past_oversized_span() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+
+        let spans = spans_of(|| {
+            for i in 0..10 {
+                let _g = new_span("span").with_property(|| {
+                    let value = if i == 5 {
+                        "x".repeat(4096)
+                    } else {
+                        i.to_string()
+                    };
+                    ("value", value.into())
+                });
+            }
+        });
+        let reporter =
+            Reporter::new(agent.local_addr().unwrap(), "oversized").with_max_packet_size(1024);
+        let err = reporter.report(TraceId::new(42), spans).unwrap_err();
+        assert!(err.to_string().contains("dropped 1 spans"));
+
+        let mut received = vec![];
+        let mut buf = vec![0; 65_536];
+        loop {
+            match agent.recv(&mut buf) {
+                Ok(len) => {
+                    assert!(len <= 1024);
+                    received.extend(decode::decode_emit_batch(&buf[..len]).unwrap().spans);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                    break
+                }
+                Err(e) => panic!("{}", e),
+            }
+        }
+        // the root and all spans but the oversized one, including those after it
+        assert_eq!(received.len(), 10);
+        assert!(received.iter().any(|s| s.tags.iter().any(|t| *t
+            == Tag::String {
+                key: "value".to_owned(),
+                value: "9".to_owned(),
+            })));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn report_async() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+
+        let reporter = Reporter::new(agent.local_addr().unwrap(), "async");
+        AsyncSpanExporter::export(&reporter, TraceId::new(42), many_spans(20))
+            .await
+            .unwrap();
+
+        let mut buf = vec![0; 65_536];
+        let len = agent.recv(&mut buf).unwrap();
+        let batch = decode::decode_emit_batch(&buf[..len]).unwrap();
+        assert_eq!(batch.process.service_name, "async");
+        assert_eq!(batch.spans.len(), 21);
+    }
+
+    #[test]
+    fn report_to_collector() {
+        let (url, server) = stand_in::serve_once();
+        let process = Process::new(String::from("collector"))
+            .with_tags(vec![("hostname", "host-1"), ("version", "1.0.0")])
+            .with_tag("pid", 42);
+        let reporter = HttpReporter::new(url, process);
+        SpanExporter::export(&reporter, TraceId::new(42), many_spans(20)).unwrap();
+
+        let request = server.join().unwrap();
+        assert_eq!(request.path, "/api/traces");
+        assert_eq!(request.header("Content-Type"), Some("application/x-thrift"));
+
+        let batch = Struct::binary_decode(&mut request.body.as_slice()).unwrap();
+        let expected_process = Struct::from(JaegerProcess {
+            service_name: "collector".to_owned(),
+            tags: vec![
+                Tag::String {
+                    key: "hostname".to_owned(),
+                    value: "host-1".to_owned(),
+                },
+                Tag::String {
+                    key: "version".to_owned(),
+                    value: "1.0.0".to_owned(),
+                },
+                Tag::Long {
+                    key: "pid".to_owned(),
+                    value: 42,
+                },
+            ],
+        });
+        match batch.fields()[0].data() {
+            Data::Struct(process) => {
+                assert_eq!(format!("{:?}", process), format!("{:?}", expected_process))
+            }
+            other => panic!("unexpected process {:?}", other),
+        }
+        match batch.fields()[1].data() {
+            Data::List(spans) => assert_eq!(spans.len(), 21),
+            other => panic!("unexpected spans {:?}", other),
+        }
+    }
+}
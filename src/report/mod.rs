@@ -1,96 +1,127 @@
-use crate::span::cycle::DefaultClock;
-use crate::Span;
-use rustracing_jaeger::thrift::agent::EmitBatchNotification;
-use rustracing_jaeger::thrift::jaeger::{
-    Batch, Process, Span as JaegerSpan, SpanRef, SpanRefKind, Tag,
-};
+pub mod batch;
+pub mod chrome;
+pub mod folded;
+pub mod jaeger;
+pub mod otlp;
+pub mod pretty;
+pub mod zipkin;
+
+pub(crate) mod http;
+
+pub use crate::report::batch::{BatchConfig, BatchReporter};
+pub use crate::report::jaeger::{HttpReporter, Reporter};
+pub use crate::report::otlp::OtlpExporter;
+pub use crate::report::zipkin::ZipkinReporter;
+
+use crate::{Span, TraceId};
 use std::error::Error;
-use std::net::{SocketAddr, UdpSocket};
-use thrift_codec::message::Message;
-use thrift_codec::CompactEncode;
+#[cfg(feature = "tokio")]
+use std::future::Future;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
 
-pub struct Reporter {
-    agent: SocketAddr,
-    service_name: &'static str,
-}
+pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync + 'static>>;
+
+#[cfg(feature = "tokio")]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A backend that collected spans are handed to.
+///
+/// `export` may buffer spans internally, so callers should invoke `flush` before relying on
+/// them having been delivered, and `shutdown` once no more spans will be exported.
+pub trait SpanExporter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()>;
 
-impl Reporter {
-    pub fn new(agent: SocketAddr, service_name: &'static str) -> Self {
-        Reporter {
-            agent,
-            service_name,
+    /// Exports spans of several traces at once. Exporters able to send spans of different
+    /// traces in one request should override this.
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        for (trace_id, spans) in traces {
+            self.export(trace_id, spans)?;
         }
+        Ok(())
     }
 
-    pub fn encode(
-        service_name: String,
-        trace_id: u64,
-        spans: Vec<Span>,
-    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync + 'static>> {
-        let anchor = DefaultClock::anchor();
-        let bn = EmitBatchNotification {
-            batch: Batch {
-                process: Process {
-                    service_name,
-                    tags: vec![],
-                },
-                spans: spans
-                    .into_iter()
-                    .map(|s| {
-                        let begin_cycles = DefaultClock::cycle_to_realtime(s.begin_cycle, anchor);
-                        let end_time = DefaultClock::cycle_to_realtime(s.end_cycle, anchor);
-                        JaegerSpan {
-                            trace_id_low: trace_id as i64,
-                            trace_id_high: 0,
-                            span_id: s.id.0 as i64,
-                            parent_span_id: s.parent_id.0 as i64,
-                            operation_name: s.event.to_string(),
-                            references: vec![SpanRef {
-                                kind: SpanRefKind::FollowsFrom,
-                                trace_id_low: trace_id as i64,
-                                trace_id_high: 0,
-                                span_id: s.parent_id.0 as i64,
-                            }],
-                            flags: 1,
-                            start_time: (begin_cycles.ns / 1_000) as i64,
-                            duration: ((end_time.ns - begin_cycles.ns) / 1_000) as i64,
-                            tags: s
-                                .properties
-                                .into_iter()
-                                .map(|p| Tag::String {
-                                    key: p.0.to_owned(),
-                                    value: p.1,
-                                })
-                                .collect(),
-                            logs: vec![],
-                        }
-                    })
-                    .collect(),
-            },
-        };
-
-        let mut bytes = Vec::new();
-        let msg = Message::from(bn);
-        msg.compact_encode(&mut bytes)?;
-        Ok(bytes)
-    }
-
-    pub fn report(
-        &self,
-        trace_id: u64,
-        spans: Vec<Span>,
-    ) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-        let local_addr: SocketAddr = if self.agent.is_ipv4() {
-            "0.0.0.0:0"
-        } else {
-            "[::]:0"
-        }
-        .parse()?;
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
 
-        let udp = UdpSocket::bind(local_addr)?;
-        let bytes = Self::encode(self.service_name.to_string(), trace_id, spans)?;
-        udp.send_to(&bytes, self.agent)?;
+    fn shutdown(&self) -> Result<()> {
+        self.flush()
+    }
+}
 
-        Ok(())
+impl<T: SpanExporter + ?Sized> SpanExporter for Box<T> {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        (**self).export(trace_id, spans)
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        (**self).export_traces(traces)
+    }
+
+    fn flush(&self) -> Result<()> {
+        (**self).flush()
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        (**self).shutdown()
+    }
+}
+
+impl<T: SpanExporter + ?Sized> SpanExporter for std::sync::Arc<T> {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        (**self).export(trace_id, spans)
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        (**self).export_traces(traces)
+    }
+
+    fn flush(&self) -> Result<()> {
+        (**self).flush()
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        (**self).shutdown()
+    }
+}
+
+/// The non-blocking counterpart of [`SpanExporter`], for exporting from within a tokio runtime
+/// without `spawn_blocking`. Requires the `tokio` feature.
+#[cfg(feature = "tokio")]
+pub trait AsyncSpanExporter: Sync {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> BoxFuture<'_, Result<()>>;
+
+    /// Exports spans of several traces at once. Exporters able to send spans of different
+    /// traces in one request should override this.
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            for (trace_id, spans) in traces {
+                self.export(trace_id, spans).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: AsyncSpanExporter + ?Sized> AsyncSpanExporter for Box<T> {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> BoxFuture<'_, Result<()>> {
+        (**self).export(trace_id, spans)
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> BoxFuture<'_, Result<()>> {
+        (**self).export_traces(traces)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: AsyncSpanExporter + ?Sized + Send> AsyncSpanExporter for std::sync::Arc<T> {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> BoxFuture<'_, Result<()>> {
+        (**self).export(trace_id, spans)
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> BoxFuture<'_, Result<()>> {
+        (**self).export_traces(traces)
     }
 }
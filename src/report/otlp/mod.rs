@@ -0,0 +1,255 @@
+pub mod proto;
+
+use crate::report::{http, Result, SpanExporter};
+#[cfg(feature = "tokio")]
+use crate::report::{AsyncSpanExporter, BoxFuture};
+use crate::span::cycle::{Anchor, DefaultClock};
+use crate::{PropertyValue, Span, TraceId};
+use prost::Message;
+
+use self::proto::any_value::Value;
+use self::proto::{
+    AnyValue, ExportTraceServiceRequest, InstrumentationScope, KeyValue, Resource, ResourceSpans,
+    ScopeSpans,
+};
+
+pub const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:4318/v1/traces";
+
+const SPAN_KIND_INTERNAL: i32 = 1;
+
+/// Exports spans as an OTLP `ExportTraceServiceRequest` over OTLP/HTTP with protobuf encoding.
+pub struct OtlpExporter {
+    endpoint: String,
+    service_name: String,
+}
+
+impl OtlpExporter {
+    /// `endpoint` is the full url spans are posted to, e.g. [`DEFAULT_ENDPOINT`].
+    pub fn new(endpoint: impl Into<String>, service_name: impl Into<String>) -> Self {
+        OtlpExporter {
+            endpoint: endpoint.into(),
+            service_name: service_name.into(),
+        }
+    }
+
+    pub fn to_request(
+        service_name: String,
+        trace_id: TraceId,
+        spans: Vec<Span>,
+    ) -> ExportTraceServiceRequest {
+        Self::to_request_traces(service_name, vec![(trace_id, spans)])
+    }
+
+    /// Builds one request carrying the spans of several traces.
+    pub fn to_request_traces(
+        service_name: String,
+        traces: Vec<(TraceId, Vec<Span>)>,
+    ) -> ExportTraceServiceRequest {
+        let anchor = DefaultClock::anchor();
+
+        ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: Some(Resource {
+                    attributes: vec![key_value("service.name", service_name)],
+                }),
+                scope_spans: vec![ScopeSpans {
+                    scope: Some(InstrumentationScope {
+                        name: env!("CARGO_PKG_NAME").to_owned(),
+                        version: env!("CARGO_PKG_VERSION").to_owned(),
+                    }),
+                    spans: traces
+                        .into_iter()
+                        .flat_map(|(trace_id, spans)| {
+                            let trace_id = trace_id.0.to_be_bytes().to_vec();
+                            spans
+                                .into_iter()
+                                .map(move |s| Self::convert(s, trace_id.clone(), anchor))
+                        })
+                        .collect(),
+                }],
+            }],
+        }
+    }
+
+    pub fn encode(service_name: String, trace_id: TraceId, spans: Vec<Span>) -> Result<Vec<u8>> {
+        Self::encode_traces(service_name, vec![(trace_id, spans)])
+    }
+
+    pub fn encode_traces(
+        service_name: String,
+        traces: Vec<(TraceId, Vec<Span>)>,
+    ) -> Result<Vec<u8>> {
+        let request = Self::to_request_traces(service_name, traces);
+        let mut bytes = Vec::with_capacity(request.encoded_len());
+        request.encode(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn report(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report_traces(vec![(trace_id, spans)])
+    }
+
+    /// Reports spans of several traces in one request.
+    pub fn report_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        let bytes = Self::encode_traces(self.service_name.clone(), traces)?;
+        http::post(&self.endpoint, "application/x-protobuf", &bytes)
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn report_async(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report_traces_async(vec![(trace_id, spans)]).await
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn report_traces_async(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        let bytes = Self::encode_traces(self.service_name.clone(), traces)?;
+        http::post_async(&self.endpoint, "application/x-protobuf", &bytes).await
+    }
+}
+
+impl OtlpExporter {
+    fn convert(s: Span, trace_id: Vec<u8>, anchor: Anchor) -> proto::Span {
+        proto::Span {
+            trace_id,
+            span_id: s.id.0.to_be_bytes().to_vec(),
+            parent_span_id: if s.parent_id.0 == 0 {
+                vec![]
+            } else {
+                s.parent_id.0.to_be_bytes().to_vec()
+            },
+            name: s.event.to_string(),
+            kind: SPAN_KIND_INTERNAL,
+            start_time_unix_nano: DefaultClock::cycle_to_realtime(s.begin_cycle, anchor).ns,
+            end_time_unix_nano: DefaultClock::cycle_to_realtime(s.end_cycle, anchor).ns,
+            attributes: s
+                .properties
+                .into_iter()
+                .map(|(k, v)| key_value(k, v))
+                .collect(),
+            events: s
+                .logs
+                .into_iter()
+                .map(|log| proto::span::Event {
+                    time_unix_nano: DefaultClock::cycle_to_realtime(log.cycle, anchor).ns,
+                    name: log.event.into_owned(),
+                    attributes: log
+                        .properties
+                        .into_iter()
+                        .map(|(k, v)| key_value(k, v))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl SpanExporter for OtlpExporter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report(trace_id, spans)
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        self.report_traces(traces)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncSpanExporter for OtlpExporter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.report_async(trace_id, spans))
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.report_traces_async(traces))
+    }
+}
+
+fn key_value(key: impl Into<String>, value: impl Into<PropertyValue>) -> KeyValue {
+    let value = match value.into() {
+        PropertyValue::String(v) => Value::StringValue(v),
+        PropertyValue::I64(v) => Value::IntValue(v),
+        PropertyValue::F64(v) => Value::DoubleValue(v),
+        PropertyValue::Bool(v) => Value::BoolValue(v),
+        PropertyValue::Bytes(v) => Value::BytesValue(v),
+    };
+
+    KeyValue {
+        key: key.into(),
+        value: Some(AnyValue { value: Some(value) }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_span;
+    use crate::report::http::stand_in;
+    use crate::testing::spans_of;
+
+    fn spans() -> Vec<Span> {
+        spans_of(|| {
+            let _g = new_span("child").with_property(|| ("key", "value".into()));
+        })
+    }
+
+    #[test]
+    fn encode() {
+        let spans = spans();
+        let root_id = spans.iter().find(|s| s.is_root()).unwrap().id;
+
+        let bytes = OtlpExporter::encode("otlp".to_owned(), TraceId::new(42), spans).unwrap();
+        let request = ExportTraceServiceRequest::decode(bytes.as_slice()).unwrap();
+
+        let resource_spans = &request.resource_spans[0];
+        assert_eq!(
+            resource_spans.resource.as_ref().unwrap().attributes,
+            vec![key_value("service.name", "otlp".to_owned())]
+        );
+
+        let spans = &resource_spans.scope_spans[0].spans;
+        assert_eq!(spans.len(), 2);
+
+        let root = spans.iter().find(|s| s.name == "root").unwrap();
+        let child = spans.iter().find(|s| s.name == "child").unwrap();
+        assert_eq!(root.trace_id, 42u128.to_be_bytes().to_vec());
+        assert!(root.parent_span_id.is_empty());
+        assert_eq!(child.parent_span_id, root_id.0.to_be_bytes().to_vec());
+        assert!(root.start_time_unix_nano <= child.start_time_unix_nano);
+        assert!(child.end_time_unix_nano <= root.end_time_unix_nano);
+        assert_eq!(child.attributes, vec![key_value("key", "value".to_owned())]);
+    }
+
+    #[test]
+    fn export_over_http() {
+        let (url, server) = stand_in::serve_once();
+        let exporter = OtlpExporter::new(format!("{}/v1/traces", url), "otlp");
+        SpanExporter::export(&exporter, TraceId::new(42), spans()).unwrap();
+
+        let request = server.join().unwrap();
+        assert_eq!(request.path, "/v1/traces");
+        assert_eq!(
+            request.header("Content-Type"),
+            Some("application/x-protobuf")
+        );
+        let decoded = ExportTraceServiceRequest::decode(request.body.as_slice()).unwrap();
+        assert_eq!(decoded.resource_spans[0].scope_spans[0].spans.len(), 2);
+    }
+
+    #[test]
+    fn export_traces_in_one_request() {
+        let (url, server) = stand_in::serve_once();
+        let exporter = OtlpExporter::new(format!("{}/v1/traces", url), "otlp");
+        // the stand-in serves a single request only
+        let traces = (1..=3).map(|i| (TraceId::new(i), spans())).collect();
+        SpanExporter::export_traces(&exporter, traces).unwrap();
+
+        let request = server.join().unwrap();
+        let decoded = ExportTraceServiceRequest::decode(request.body.as_slice()).unwrap();
+        let spans = &decoded.resource_spans[0].scope_spans[0].spans;
+        assert_eq!(spans.len(), 6);
+        for i in 1..=3u128 {
+            let trace_id = i.to_be_bytes().to_vec();
+            assert_eq!(spans.iter().filter(|s| s.trace_id == trace_id).count(), 2);
+        }
+    }
+}
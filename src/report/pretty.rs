@@ -0,0 +1,138 @@
+//! Formats a collected trace as an indented tree for tests and logs:
+//!
+//! ```text
+//! root 1.2ms +0ns
+//! ├─ child 805.0µs +12.3µs key=value
+//! │  └─ grandchild 3.1µs +20.0µs
+//! └─ spawned 250.6µs +900.1µs
+//! ```
+//!
+//! Siblings are ordered by start time. Each line shows the duration of the span and its offset
+//! from the start of the earliest span.
+
+use crate::span::cycle::{Anchor, Cycle, DefaultClock};
+use crate::span::tree::SpanTree;
+use crate::Span;
+use std::fmt;
+
+pub struct TreeDisplay<'a> {
+    spans: &'a [Span],
+}
+
+pub fn display_tree(spans: &[Span]) -> TreeDisplay<'_> {
+    TreeDisplay { spans }
+}
+
+impl fmt::Display for TreeDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let anchor = DefaultClock::anchor();
+        let tree = SpanTree::new(self.spans);
+        let origin = tree
+            .roots()
+            .first()
+            .map(|&i| ns(tree.span(i).begin_cycle, anchor))
+            .unwrap_or_default();
+
+        // (span index, indent of its line, indent of its children's lines)
+        let mut pending: Vec<(usize, String, String)> = tree
+            .roots()
+            .iter()
+            .rev()
+            .map(|&i| (i, String::new(), String::new()))
+            .collect();
+        while let Some((index, indent, child_indent)) = pending.pop() {
+            let span = tree.span(index);
+            let begin = ns(span.begin_cycle, anchor);
+            let end = ns(span.end_cycle, anchor);
+            write!(
+                f,
+                "{}{} {} +{}",
+                indent,
+                span.event,
+                HumanDuration(end.saturating_sub(begin)),
+                HumanDuration(begin.saturating_sub(origin)),
+            )?;
+            for (k, v) in &span.properties {
+                write!(f, " {}={}", k, v)?;
+            }
+            writeln!(f)?;
+
+            let children = tree.children(index);
+            for (n, &c) in children.iter().enumerate().rev() {
+                let (branch, next) = if n + 1 == children.len() {
+                    ("└─ ", "   ")
+                } else {
+                    ("├─ ", "│  ")
+                };
+                pending.push((
+                    c,
+                    format!("{}{}", child_indent, branch),
+                    format!("{}{}", child_indent, next),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct HumanDuration(u64);
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ns = self.0;
+        if ns < 1_000 {
+            write!(f, "{}ns", ns)
+        } else if ns < 1_000_000 {
+            write!(f, "{:.1}µs", ns as f64 / 1e3)
+        } else if ns < 1_000_000_000 {
+            write!(f, "{:.1}ms", ns as f64 / 1e6)
+        } else {
+            write!(f, "{:.2}s", ns as f64 / 1e9)
+        }
+    }
+}
+
+fn ns(cycle: Cycle, anchor: Anchor) -> u64 {
+    DefaultClock::cycle_to_realtime(cycle, anchor).ns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::spans_of;
+    use crate::{new_span, spawn_scope};
+
+    #[test]
+    fn display() {
+        let spans = spans_of(|| {
+            {
+                let _g = new_span("child").with_property(|| ("key", "value".into()));
+                let _g = new_span("grandchild");
+            }
+
+            let scope = spawn_scope("spawned");
+            std::thread::spawn(move || {
+                let _sg = scope.start_scope();
+            })
+            .join()
+            .unwrap();
+        });
+
+        let out = display_tree(&spans).to_string();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("root ") && lines[0].ends_with(" +0ns"));
+        assert!(lines[1].starts_with("├─ child ") && lines[1].ends_with(" key=value"));
+        assert!(lines[2].starts_with("│  └─ grandchild "));
+        assert!(lines[3].starts_with("└─ spawned "));
+    }
+
+    #[test]
+    fn human_duration() {
+        assert_eq!(HumanDuration(999).to_string(), "999ns");
+        assert_eq!(HumanDuration(12_345).to_string(), "12.3µs");
+        assert_eq!(HumanDuration(1_500_000).to_string(), "1.5ms");
+        assert_eq!(HumanDuration(2_345_000_000).to_string(), "2.35s");
+    }
+}
@@ -0,0 +1,295 @@
+use crate::report::{http, Result, SpanExporter};
+#[cfg(feature = "tokio")]
+use crate::report::{AsyncSpanExporter, BoxFuture};
+use crate::span::cycle::DefaultClock;
+use crate::span::span_id::SpanId;
+use crate::{Span, TraceId};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:9411";
+
+/// A span in the [Zipkin v2] JSON model.
+///
+/// [Zipkin v2]: https://zipkin.io/zipkin-api/#/default/post_spans
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZipkinSpan {
+    pub trace_id: String,
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    pub name: String,
+    /// Epoch microseconds of the start of this span.
+    pub timestamp: u64,
+    /// Duration in microseconds.
+    pub duration: u64,
+    pub local_endpoint: Endpoint,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+/// A timestamped event. Properties of a log are appended to `value` as `key=value` pairs.
+#[derive(Clone, Debug, Serialize)]
+pub struct Annotation {
+    /// Epoch microseconds of the event.
+    pub timestamp: u64,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoint {
+    pub service_name: String,
+}
+
+/// Posts spans as Zipkin v2 JSON to `{endpoint}/api/v2/spans`.
+pub struct ZipkinReporter {
+    endpoint: String,
+    service_name: String,
+}
+
+impl ZipkinReporter {
+    /// `endpoint` is the base url of the Zipkin server, e.g. [`DEFAULT_ENDPOINT`].
+    pub fn new(endpoint: impl Into<String>, service_name: impl Into<String>) -> Self {
+        ZipkinReporter {
+            endpoint: endpoint.into(),
+            service_name: service_name.into(),
+        }
+    }
+
+    pub fn convert(service_name: &str, trace_id: TraceId, spans: Vec<Span>) -> Vec<ZipkinSpan> {
+        let anchor = DefaultClock::anchor();
+        let trace_id = trace_id.to_string();
+
+        spans
+            .into_iter()
+            .map(|s| {
+                let begin_time = DefaultClock::cycle_to_realtime(s.begin_cycle, anchor);
+                let end_time = DefaultClock::cycle_to_realtime(s.end_cycle, anchor);
+                ZipkinSpan {
+                    trace_id: trace_id.clone(),
+                    id: span_id_to_hex(s.id),
+                    parent_id: if s.parent_id.0 == 0 {
+                        None
+                    } else {
+                        Some(span_id_to_hex(s.parent_id))
+                    },
+                    name: s.event.to_string(),
+                    timestamp: begin_time.ns / 1_000,
+                    duration: (end_time.ns - begin_time.ns) / 1_000,
+                    local_endpoint: Endpoint {
+                        service_name: service_name.to_owned(),
+                    },
+                    annotations: s
+                        .logs
+                        .into_iter()
+                        .map(|log| Annotation {
+                            timestamp: DefaultClock::cycle_to_realtime(log.cycle, anchor).ns
+                                / 1_000,
+                            value: log.properties.into_iter().fold(
+                                log.event.into_owned(),
+                                |mut value, (k, v)| {
+                                    value.push_str(&format!(" {}={}", k, v));
+                                    value
+                                },
+                            ),
+                        })
+                        .collect(),
+                    tags: s
+                        .properties
+                        .into_iter()
+                        .map(|(k, v)| (k.into_owned(), v.to_string()))
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn encode(service_name: &str, trace_id: TraceId, spans: Vec<Span>) -> Result<Vec<u8>> {
+        Self::encode_traces(service_name, vec![(trace_id, spans)])
+    }
+
+    /// Encodes the spans of several traces into one list, as each span carries its trace id.
+    pub fn encode_traces(service_name: &str, traces: Vec<(TraceId, Vec<Span>)>) -> Result<Vec<u8>> {
+        let spans: Vec<_> = traces
+            .into_iter()
+            .flat_map(|(trace_id, spans)| Self::convert(service_name, trace_id, spans))
+            .collect();
+        Ok(serde_json::to_vec(&spans)?)
+    }
+
+    pub fn report(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report_traces(vec![(trace_id, spans)])
+    }
+
+    /// Reports spans of several traces in one request.
+    pub fn report_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        let bytes = Self::encode_traces(&self.service_name, traces)?;
+        http::post(&self.url(), "application/json", &bytes)
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn report_async(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report_traces_async(vec![(trace_id, spans)]).await
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn report_traces_async(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        let bytes = Self::encode_traces(&self.service_name, traces)?;
+        http::post_async(&self.url(), "application/json", &bytes).await
+    }
+
+    fn url(&self) -> String {
+        format!("{}/api/v2/spans", self.endpoint.trim_end_matches('/'))
+    }
+}
+
+impl SpanExporter for ZipkinReporter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> Result<()> {
+        self.report(trace_id, spans)
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> Result<()> {
+        self.report_traces(traces)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncSpanExporter for ZipkinReporter {
+    fn export(&self, trace_id: TraceId, spans: Vec<Span>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.report_async(trace_id, spans))
+    }
+
+    fn export_traces(&self, traces: Vec<(TraceId, Vec<Span>)>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.report_traces_async(traces))
+    }
+}
+
+fn span_id_to_hex(id: SpanId) -> String {
+    format!("{:016x}", id.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::http::stand_in;
+    use crate::testing::spans_of;
+    use crate::{log_event_with_properties, new_span};
+    use serde_json::Value;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn spans() -> Vec<Span> {
+        spans_of(|| {
+            let _g = new_span("child").with_property(|| ("key", "value".into()));
+            log_event_with_properties("cache miss", || vec![("table", "t1".into())]);
+            std::thread::sleep(Duration::from_millis(2));
+        })
+    }
+
+    fn encoded(spans: Vec<Span>) -> Vec<Value> {
+        let bytes = ZipkinReporter::encode("zipkin", TraceId::new(0xabc), spans).unwrap();
+        let json: Value = serde_json::from_slice(&bytes).unwrap();
+        json.as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn ids() {
+        let spans = spans();
+        let root_id = spans.iter().find(|s| s.is_root()).unwrap().id;
+        let spans = encoded(spans);
+
+        let root = spans.iter().find(|s| s["name"] == "root").unwrap();
+        let child = spans.iter().find(|s| s["name"] == "child").unwrap();
+        // 128-bit trace ids and 64-bit span ids as zero-padded lower-case hex
+        assert_eq!(root["traceId"], "00000000000000000000000000000abc");
+        assert_eq!(child["traceId"], root["traceId"]);
+        assert_eq!(root["id"], format!("{:016x}", root_id.0));
+        assert_eq!(child["id"].as_str().unwrap().len(), 16);
+        // the root has no parent rather than a zero one
+        assert!(root.get("parentId").is_none());
+        assert_eq!(child["parentId"], root["id"]);
+    }
+
+    #[test]
+    fn microseconds() {
+        let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let spans = encoded(spans());
+        let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+        let child = spans.iter().find(|s| s["name"] == "child").unwrap();
+        let timestamp = child["timestamp"].as_u64().unwrap();
+        assert!(before.as_micros() as u64 <= timestamp + 1_000);
+        assert!(timestamp <= after.as_micros() as u64 + 1_000);
+
+        let duration = child["duration"].as_u64().unwrap();
+        assert!(duration >= 2_000);
+        assert!(duration < (after - before).as_micros() as u64 + 1_000);
+
+        let annotation = &child["annotations"][0];
+        assert_eq!(annotation["value"], "cache miss table=t1");
+        let logged_at = annotation["timestamp"].as_u64().unwrap();
+        assert!(timestamp <= logged_at && logged_at <= timestamp + duration);
+    }
+
+    #[test]
+    fn local_endpoint_and_tags() {
+        let spans = encoded(spans());
+
+        assert!(spans
+            .iter()
+            .all(|s| s["localEndpoint"] == serde_json::json!({ "serviceName": "zipkin" })));
+        let root = spans.iter().find(|s| s["name"] == "root").unwrap();
+        let child = spans.iter().find(|s| s["name"] == "child").unwrap();
+        assert!(root.get("tags").is_none());
+        assert!(root.get("annotations").is_none());
+        assert_eq!(child["tags"], serde_json::json!({ "key": "value" }));
+    }
+
+    #[test]
+    fn export_over_http() {
+        let (url, server) = stand_in::serve_once();
+        // a trailing slash of the base url is not doubled
+        let reporter = ZipkinReporter::new(format!("{}/", url), "zipkin");
+        SpanExporter::export(&reporter, TraceId::new(0xabc), spans()).unwrap();
+
+        let request = server.join().unwrap();
+        assert_eq!(request.path, "/api/v2/spans");
+        assert_eq!(request.header("Content-Type"), Some("application/json"));
+    }
+
+    #[test]
+    fn export_traces_in_one_request() {
+        let (url, server) = stand_in::serve_once();
+        let reporter = ZipkinReporter::new(url, "zipkin");
+        // the stand-in serves a single request only
+        let traces = (1..=3).map(|i| (TraceId::new(i), spans())).collect();
+        SpanExporter::export_traces(&reporter, traces).unwrap();
+
+        let request = server.join().unwrap();
+        let json: Value = serde_json::from_slice(&request.body).unwrap();
+        let spans = json.as_array().unwrap();
+        assert_eq!(spans.len(), 6);
+        for i in 1..=3u128 {
+            let trace_id = format!("{:032x}", i);
+            assert_eq!(spans.iter().filter(|s| s["traceId"] == trace_id).count(), 2);
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn export_async() {
+        let (url, server) = stand_in::serve_once();
+        let reporter = ZipkinReporter::new(url, "zipkin");
+        AsyncSpanExporter::export(&reporter, TraceId::new(0xabc), spans())
+            .await
+            .unwrap();
+
+        let request = server.join().unwrap();
+        assert_eq!(request.path, "/api/v2/spans");
+        let json: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+    }
+}
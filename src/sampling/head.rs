@@ -0,0 +1,205 @@
+use crate::TraceId;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Decides whether to record a trace before it starts, given its id and root event.
+///
+/// See [`root_scope_with_sampler`](crate::root_scope_with_sampler).
+pub trait HeadSampler: Send + Sync {
+    fn sample(&self, trace_id: TraceId, event: &str) -> bool;
+}
+
+impl<F: Fn(TraceId, &str) -> bool + Send + Sync> HeadSampler for F {
+    fn sample(&self, trace_id: TraceId, event: &str) -> bool {
+        self(trace_id, event)
+    }
+}
+
+/// Records the given ratio of traces, chosen by trace id like
+/// [`Probabilistic`](super::Probabilistic).
+#[derive(Clone, Copy, Debug)]
+pub struct Ratio(pub f64);
+
+impl HeadSampler for Ratio {
+    fn sample(&self, trace_id: TraceId, _event: &str) -> bool {
+        super::sampled_by_ratio(trace_id, self.0)
+    }
+}
+
+/// Records at most the given number of traces per second for each root event, allowing bursts
+/// of up to a second's worth, or of one trace for rates below one per second. A rate of zero
+/// records nothing.
+///
+/// Up to [`RateLimit::MAX_EVENTS`] root events are tracked at a time, split into shards that
+/// are locked separately. Beyond that, an event seen for the first time is only recorded once
+/// another one of its shard has been idle long enough to forget.
+#[derive(Debug)]
+pub struct RateLimit {
+    per_second: f64,
+    shards: Vec<Mutex<HashMap<String, Bucket>>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimit {
+    /// The number of root events tracked at a time.
+    pub const MAX_EVENTS: usize = 1024;
+
+    const SHARDS: usize = 16;
+
+    /// Panics if `per_second` is negative or NaN.
+    pub fn new(per_second: f64) -> Self {
+        assert!(
+            per_second >= 0.0,
+            "expect a non-negative rate, got {}",
+            per_second
+        );
+
+        RateLimit {
+            per_second,
+            shards: (0..Self::SHARDS)
+                .map(|_| Mutex::new(HashMap::new()))
+                .collect(),
+        }
+    }
+
+    fn shard(&self, event: &str) -> &Mutex<HashMap<String, Bucket>> {
+        let mut hasher = DefaultHasher::new();
+        event.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % Self::SHARDS]
+    }
+
+    fn capacity(&self) -> f64 {
+        self.per_second.max(1.0)
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.capacity());
+        bucket.refilled_at = now;
+    }
+}
+
+impl HeadSampler for RateLimit {
+    fn sample(&self, _trace_id: TraceId, event: &str) -> bool {
+        if self.per_second == 0.0 {
+            return false;
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.shard(event).lock().unwrap();
+        let bucket = match buckets.get_mut(event) {
+            Some(bucket) => {
+                self.refill(bucket, now);
+                bucket
+            }
+            None => {
+                if buckets.len() >= Self::MAX_EVENTS / Self::SHARDS {
+                    // a full bucket behaves like a new one
+                    buckets.retain(|_, bucket| {
+                        self.refill(bucket, now);
+                        bucket.tokens < self.capacity()
+                    });
+                    if buckets.len() >= Self::MAX_EVENTS / Self::SHARDS {
+                        return false;
+                    }
+                }
+                buckets.entry(event.to_owned()).or_insert(Bucket {
+                    tokens: self.capacity(),
+                    refilled_at: now,
+                })
+            }
+        };
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_span, root_scope_with_sampler, spawn_scope, CollectOptions};
+
+    #[test]
+    fn samplers() {
+        let rate_limit = RateLimit::new(2.0);
+        let sampled = (0..5)
+            .filter(|_| rate_limit.sample(TraceId::new(0), "get"))
+            .count();
+        assert_eq!(sampled, 2);
+        assert!(rate_limit.sample(TraceId::new(0), "put"));
+
+        assert!(Ratio(0.5).sample(TraceId::new(u128::from(u64::MAX / 4)), "get"));
+        assert!(!Ratio(0.0).sample(TraceId::new(0), "get"));
+
+        let only_puts = |_, event: &str| event == "put";
+        assert!(!only_puts.sample(TraceId::new(0), "get"));
+    }
+
+    #[test]
+    fn rate_limit() {
+        let rate_limit = RateLimit::new(0.5);
+        assert!(rate_limit.sample(TraceId::new(0), "get"));
+        assert!(!rate_limit.sample(TraceId::new(0), "get"));
+
+        let sampled = (1..2 * RateLimit::MAX_EVENTS)
+            .filter(|i| rate_limit.sample(TraceId::new(0), &i.to_string()))
+            .count();
+        assert_eq!(sampled, RateLimit::MAX_EVENTS - 1);
+        let tracked: usize = rate_limit
+            .shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum();
+        assert_eq!(tracked, RateLimit::MAX_EVENTS);
+
+        let never = RateLimit::new(0.0);
+        assert!(!never.sample(TraceId::new(0), "get"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_rate() {
+        RateLimit::new(-1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn nan_rate() {
+        RateLimit::new(f64::NAN);
+    }
+
+    #[test]
+    fn unsampled() {
+        let never = |_, _: &str| false;
+        let (scope, collector) = root_scope_with_sampler("root", &never);
+        assert_eq!(scope.trace_id(), None);
+        {
+            let _sg = scope.start_scope();
+            let _g = new_span("child");
+            let spawned = spawn_scope("spawned");
+            let _sg = spawned.start_scope();
+        }
+        let trace = collector.collect(CollectOptions::new().with_sync(true));
+        drop(scope);
+        assert!(trace.spans.is_empty());
+
+        let always = |_, _: &str| true;
+        let (scope, collector) = root_scope_with_sampler("root", &always);
+        drop(scope);
+        let trace = collector.collect(CollectOptions::new().with_sync(true));
+        assert_eq!(trace.spans.len(), 1);
+    }
+}
@@ -0,0 +1,16 @@
+//! Policies deciding which traces are kept.
+
+mod head;
+mod tail;
+
+pub use self::head::{HeadSampler, RateLimit, Ratio};
+pub use self::tail::{
+    And, HasError, LatencyThreshold, Or, Probabilistic, PropertyMatch, TailSampler,
+};
+
+use crate::TraceId;
+
+/// Samples `ratio` of all trace ids, the same ones for the same ratio.
+fn sampled_by_ratio(trace_id: TraceId, ratio: f64) -> bool {
+    ratio >= 1.0 || trace_id.low() < (ratio * u64::MAX as f64) as u64
+}
@@ -0,0 +1,164 @@
+use crate::span::cycle::DefaultClock;
+use crate::{PropertyValue, Span, Trace};
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// Decides whether to keep a trace once all of its spans are collected.
+///
+/// Traces that are not sampled are reduced to their root span, see
+/// [`CollectOptions::with_tail_sampler`](crate::CollectOptions::with_tail_sampler).
+pub trait TailSampler: Send + Sync {
+    fn sample(&self, trace: &Trace) -> bool;
+
+    /// Keeps traces sampled by both `self` and `other`.
+    fn and<S: TailSampler>(self, other: S) -> And<Self, S>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// Keeps traces sampled by `self`, `other` or both.
+    fn or<S: TailSampler>(self, other: S) -> Or<Self, S>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+}
+
+impl<F: Fn(&Trace) -> bool + Send + Sync> TailSampler for F {
+    fn sample(&self, trace: &Trace) -> bool {
+        self(trace)
+    }
+}
+
+/// Keeps traces whose root span lasts at least the threshold. Traces without a root span are
+/// kept.
+#[derive(Clone, Copy, Debug)]
+pub struct LatencyThreshold(pub Duration);
+
+impl TailSampler for LatencyThreshold {
+    fn sample(&self, trace: &Trace) -> bool {
+        match trace.root_span() {
+            Some(root) => duration(root) >= self.0,
+            None => true,
+        }
+    }
+}
+
+/// Keeps traces with a span marked by an `error` property set to `true`, as in the OpenTracing
+/// semantic conventions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HasError;
+
+impl TailSampler for HasError {
+    fn sample(&self, trace: &Trace) -> bool {
+        PropertyMatch::new("error", true).sample(trace)
+    }
+}
+
+/// Keeps traces with a span having the given property.
+#[derive(Clone, Debug)]
+pub struct PropertyMatch {
+    key: Cow<'static, str>,
+    value: PropertyValue,
+}
+
+impl PropertyMatch {
+    pub fn new(key: impl Into<Cow<'static, str>>, value: impl Into<PropertyValue>) -> Self {
+        PropertyMatch {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+}
+
+impl TailSampler for PropertyMatch {
+    fn sample(&self, trace: &Trace) -> bool {
+        trace.spans.iter().any(|s| {
+            s.properties
+                .iter()
+                .any(|(k, v)| *k == self.key && *v == self.value)
+        })
+    }
+}
+
+/// Keeps the given ratio of traces, chosen by trace id so that every service sampling the same
+/// trace with the same ratio makes the same decision.
+#[derive(Clone, Copy, Debug)]
+pub struct Probabilistic(pub f64);
+
+impl TailSampler for Probabilistic {
+    fn sample(&self, trace: &Trace) -> bool {
+        super::sampled_by_ratio(trace.trace_id, self.0)
+    }
+}
+
+/// See [`TailSampler::and`].
+#[derive(Clone, Copy, Debug)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: TailSampler, B: TailSampler> TailSampler for And<A, B> {
+    fn sample(&self, trace: &Trace) -> bool {
+        self.0.sample(trace) && self.1.sample(trace)
+    }
+}
+
+/// See [`TailSampler::or`].
+#[derive(Clone, Copy, Debug)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: TailSampler, B: TailSampler> TailSampler for Or<A, B> {
+    fn sample(&self, trace: &Trace) -> bool {
+        self.0.sample(trace) || self.1.sample(trace)
+    }
+}
+
+fn duration(span: &Span) -> Duration {
+    let anchor = DefaultClock::anchor();
+    let begin = DefaultClock::cycle_to_realtime(span.begin_cycle, anchor).ns;
+    let end = DefaultClock::cycle_to_realtime(span.end_cycle, anchor).ns;
+    Duration::from_nanos(end.saturating_sub(begin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::trace_of;
+    use crate::{new_span, TraceId};
+
+    fn trace(error: bool) -> Trace {
+        trace_of(|| {
+            let _g = new_span("child")
+                .with_property(|| ("error", error.into()))
+                .with_property(|| ("table", "t1".into()));
+        })
+    }
+
+    #[test]
+    fn policies() {
+        let ok = trace(false);
+        let failed = trace(true);
+
+        assert!(!HasError.sample(&ok));
+        assert!(HasError.sample(&failed));
+        assert!(PropertyMatch::new("table", "t1").sample(&ok));
+        assert!(!PropertyMatch::new("table", "t2").sample(&ok));
+        assert!(LatencyThreshold(Duration::from_secs(0)).sample(&ok));
+        assert!(!LatencyThreshold(Duration::from_secs(60)).sample(&ok));
+
+        let mut trace = ok.clone();
+        trace.trace_id = TraceId::new(u128::from(u64::MAX / 4));
+        assert!(Probabilistic(0.5).sample(&trace));
+        assert!(!Probabilistic(0.1).sample(&trace));
+        assert!(!Probabilistic(0.0).sample(&trace));
+
+        let slow_or_failed = LatencyThreshold(Duration::from_secs(60)).or(HasError);
+        assert!(!slow_or_failed.sample(&ok));
+        assert!(slow_or_failed.sample(&failed));
+        let failed_on_t1 = HasError.and(PropertyMatch::new("table", "t1"));
+        assert!(!failed_on_t1.sample(&ok));
+        assert!(failed_on_t1.sample(&failed));
+    }
+}
@@ -1,26 +1,108 @@
 pub mod cycle;
+pub mod name;
+pub mod property;
 pub mod span_id;
 pub mod span_queue;
+pub mod tree;
 
 use crate::span::cycle::Cycle;
+pub use crate::span::name::SpanName;
 use crate::span::span_id::SpanId;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Deref;
+
+pub use crate::span::property::PropertyValue;
+
+/// How a span relates to its parent, following the OpenTracing reference types.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RefKind {
+    /// The parent depends on the result of the span, e.g. a synchronous call.
+    ChildOf,
+    /// The parent does not wait for the span, e.g. a spawned task.
+    FollowsFrom,
+}
+
+/// An event recorded at a point in time during a span, e.g. a retry or a cache miss.
+#[derive(Clone, Debug)]
+pub struct Log {
+    pub cycle: Cycle,
+    pub event: Cow<'static, str>,
+    pub properties: Vec<(Cow<'static, str>, PropertyValue)>,
+}
+
+/// The logs of a span.
+///
+/// Few spans have logs, so they are kept out of line and cost a span one pointer until the
+/// first log.
+#[allow(clippy::box_collection)]
+#[derive(Clone, Default)]
+pub struct Logs(Option<Box<Vec<Log>>>);
+
+impl Logs {
+    #[inline]
+    pub fn push(&mut self, log: Log) {
+        self.0.get_or_insert_with(Default::default).push(log);
+    }
+}
+
+impl Deref for Logs {
+    type Target = [Log];
+
+    #[inline]
+    fn deref(&self) -> &[Log] {
+        match &self.0 {
+            Some(logs) => logs,
+            None => &[],
+        }
+    }
+}
+
+impl fmt::Debug for Logs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl IntoIterator for Logs {
+    type Item = Log;
+    type IntoIter = std::vec::IntoIter<Log>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.map(|logs| *logs).unwrap_or_default().into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Logs {
+    type Item = &'a Log;
+    type IntoIter = std::slice::Iter<'a, Log>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Span {
     pub id: SpanId,
     pub parent_id: SpanId,
+    pub ref_kind: RefKind,
     pub begin_cycle: Cycle,
-    pub event: &'static str,
-    pub properties: Vec<(&'static str, String)>,
+    pub event: SpanName,
+    pub properties: Vec<(Cow<'static, str>, PropertyValue)>,
+    pub logs: Logs,
 
     // post processing will write this
     pub end_cycle: Cycle,
 
-    // for local queue implementation
-    pub(crate) _descendant_count: usize,
+    // for local queue implementation, narrow to keep spans small
+    pub(crate) _descendant_count: u32,
 
     // a tag
     pub(crate) _is_spawn_span: bool,
+
+    // whether the span is created by a scope rather than `new_span`
+    pub(crate) _is_scope_span: bool,
 }
 
 impl Span {
@@ -29,24 +111,27 @@ impl Span {
         id: SpanId,
         parent_id: SpanId,
         begin_cycles: Cycle,
-        event: &'static str,
+        event: SpanName,
     ) -> Self {
         Span {
             id,
             parent_id,
+            ref_kind: RefKind::ChildOf,
             begin_cycle: begin_cycles,
             event,
             properties: vec![],
+            logs: Logs::default(),
             end_cycle: Cycle::default(),
             _descendant_count: 0,
             _is_spawn_span: false,
+            _is_scope_span: false,
         }
     }
 
     #[inline]
     pub(crate) fn end_with(&mut self, end_cycles: Cycle, descendant_count: usize) {
         self.end_cycle = end_cycles;
-        self._descendant_count = descendant_count;
+        self._descendant_count = descendant_count as u32;
     }
 
     #[inline]
@@ -67,19 +152,27 @@ impl Into<Span> for &Span {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct ScopeSpan {
     pub id: SpanId,
     pub parent_id: SpanId,
+    pub ref_kind: RefKind,
     pub begin_cycles: Cycle,
-    pub event: &'static str,
+    pub event: SpanName,
 }
 
 impl ScopeSpan {
-    pub fn new(id: SpanId, parent_id: SpanId, begin_cycles: Cycle, event: &'static str) -> Self {
+    pub fn new(
+        id: SpanId,
+        parent_id: SpanId,
+        ref_kind: RefKind,
+        begin_cycles: Cycle,
+        event: SpanName,
+    ) -> Self {
         ScopeSpan {
             id,
             parent_id,
+            ref_kind,
             begin_cycles,
             event,
         }
@@ -89,12 +182,27 @@ impl ScopeSpan {
         Span {
             id: self.id,
             parent_id: self.parent_id,
+            ref_kind: self.ref_kind,
             begin_cycle: self.begin_cycles,
-            event: self.event,
+            event: self.event.clone(),
             properties: vec![],
+            logs: Logs::default(),
             end_cycle: end_cycles,
             _descendant_count: 0,
             _is_spawn_span: false,
+            _is_scope_span: true,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn span_size() {
+        // every span moves through the local queue and the collector, see benches/trace.rs
+        assert!(std::mem::size_of::<Span>() <= 96);
+        assert_eq!(std::mem::size_of::<Logs>(), std::mem::size_of::<usize>());
+    }
+}
@@ -0,0 +1,111 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The value of a span property.
+///
+/// Values are stored as recorded, so numbers are neither formatted on the hot path nor
+/// exported as strings.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    String(String),
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+}
+
+/// Formats the value the way backends without typed values show it. Bytes are written in hex.
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::String(v) => f.write_str(v),
+            PropertyValue::I64(v) => write!(f, "{}", v),
+            PropertyValue::F64(v) => write!(f, "{}", v),
+            PropertyValue::Bool(v) => write!(f, "{}", v),
+            PropertyValue::Bytes(v) => v.iter().try_for_each(|b| write!(f, "{:02x}", b)),
+        }
+    }
+}
+
+impl From<String> for PropertyValue {
+    fn from(v: String) -> Self {
+        PropertyValue::String(v)
+    }
+}
+
+impl From<&str> for PropertyValue {
+    fn from(v: &str) -> Self {
+        PropertyValue::String(v.to_owned())
+    }
+}
+
+impl From<Cow<'_, str>> for PropertyValue {
+    fn from(v: Cow<'_, str>) -> Self {
+        PropertyValue::String(v.into_owned())
+    }
+}
+
+impl From<bool> for PropertyValue {
+    fn from(v: bool) -> Self {
+        PropertyValue::Bool(v)
+    }
+}
+
+impl From<Vec<u8>> for PropertyValue {
+    fn from(v: Vec<u8>) -> Self {
+        PropertyValue::Bytes(v)
+    }
+}
+
+impl From<&[u8]> for PropertyValue {
+    fn from(v: &[u8]) -> Self {
+        PropertyValue::Bytes(v.to_vec())
+    }
+}
+
+macro_rules! impl_from_lossless {
+    ($variant:ident, $to:ty, $($from:ty),*) => {
+        $(
+            impl From<$from> for PropertyValue {
+                fn from(v: $from) -> Self {
+                    PropertyValue::$variant(<$to>::from(v))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_lossless!(I64, i64, i8, i16, i32, i64, u8, u16, u32);
+impl_from_lossless!(F64, f64, f32, f64);
+
+macro_rules! impl_from_saturating {
+    ($($from:ty),*) => {
+        $(
+            /// Counts beyond `i64::MAX` saturate to `i64::MAX`.
+            impl From<$from> for PropertyValue {
+                fn from(v: $from) -> Self {
+                    PropertyValue::I64(i64::try_from(v).unwrap_or(i64::MAX))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_saturating!(u64, usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(PropertyValue::from("str").to_string(), "str");
+        assert_eq!(PropertyValue::from(-42i32).to_string(), "-42");
+        assert_eq!(PropertyValue::from(1.5f32).to_string(), "1.5");
+        assert_eq!(PropertyValue::from(true).to_string(), "true");
+        assert_eq!(PropertyValue::from(&[0x0a, 0xff][..]).to_string(), "0aff");
+        assert_eq!(PropertyValue::from(7usize), PropertyValue::I64(7));
+        assert_eq!(PropertyValue::from(u64::MAX), PropertyValue::I64(i64::MAX));
+    }
+}
@@ -1,4 +1,7 @@
 use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::sync::atomic::{AtomicU16, AtomicU32, Ordering};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Default)]
@@ -10,6 +13,33 @@ impl SpanId {
     }
 }
 
+/// Identifies a trace. Generated randomly by `root_scope` and shared by all spans it collects.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Default)]
+pub struct TraceId(pub u128);
+
+impl TraceId {
+    pub fn new(id: u128) -> Self {
+        TraceId(id)
+    }
+
+    #[inline]
+    pub fn high(&self) -> u64 {
+        (self.0 >> 64) as u64
+    }
+
+    #[inline]
+    pub fn low(&self) -> u64 {
+        self.0 as u64
+    }
+}
+
+/// Formats the id as 32 lowercase hex digits, the form used by Jaeger, Zipkin and W3C.
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
 pub struct DefaultIdGenerator;
 
 static NEXT_SNOWFLAKE_ID_PREFIX: AtomicU16 = AtomicU16::new(0);
@@ -21,6 +51,20 @@ thread_local! {
     static SNOWFLACK_ID_GENERATOR: Cell<(u16, u16)> = Cell::new((next_snowflake_id_prefix(), 0))
 }
 
+thread_local! {
+    // one generator for each half, so that neither half follows from the other
+    static TRACE_ID_RNG: Cell<(u64, u64)> = Cell::new((trace_id_seed(), trace_id_seed()))
+}
+
+/// Seeds from the random keys of `RandomState`, which differ on every call.
+fn trace_id_seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    std::thread::current().id().hash(&mut hasher);
+    std::time::SystemTime::now().hash(&mut hasher);
+    // xorshift gets stuck at zero
+    hasher.finish() | 1
+}
+
 /// Set by user
 static ID_PREFIX: AtomicU32 = AtomicU32::new(0);
 
@@ -44,6 +88,19 @@ impl DefaultIdGenerator {
         })
     }
 
+    /// Returns a random trace id. Uses two per-thread xorshift generators seeded independently,
+    /// which are fast but unsuitable for anything security related.
+    #[inline]
+    pub fn next_trace_id() -> TraceId {
+        TRACE_ID_RNG.with(|rng| {
+            let (high, low) = rng.get();
+            let (high, low) = (xorshift(high), xorshift(low));
+            rng.set((high, low));
+
+            TraceId::new(((high as u128) << 64) | low as u128)
+        })
+    }
+
     #[inline]
     pub fn set_prefix(prefix: u32) {
         ID_PREFIX.store(prefix, Ordering::Release);
@@ -54,3 +111,28 @@ impl DefaultIdGenerator {
         ID_PREFIX.load(Ordering::Acquire)
     }
 }
+
+#[inline]
+fn xorshift(mut x: u64) -> u64 {
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn independent_halves() {
+        let a = DefaultIdGenerator::next_trace_id();
+        let b = DefaultIdGenerator::next_trace_id();
+        assert_ne!(a, b);
+        // the halves come from different generators rather than one sequence
+        assert_ne!(xorshift(a.high()), a.low());
+        assert_ne!(xorshift(a.low()), b.high());
+        assert_eq!(xorshift(a.high()), b.high());
+        assert_eq!(xorshift(a.low()), b.low());
+    }
+}
@@ -1,12 +1,16 @@
 use crate::collections::queue::FixedIndexQueue;
 use crate::span::cycle::{Cycle, DefaultClock};
 use crate::span::span_id::{DefaultIdGenerator, SpanId};
-use crate::span::{ScopeSpan, Span};
+use crate::span::{Log, PropertyValue, RefKind, ScopeSpan, Span, SpanName};
+use std::borrow::Cow;
 use std::collections::VecDeque;
 
 pub struct SpanQueue {
     span_queue: FixedIndexQueue<Span>,
     next_parent_id: SpanId,
+
+    // indexes of started but unfinished spans, the innermost last
+    active_spans: Vec<usize>,
 }
 
 impl SpanQueue {
@@ -14,14 +18,16 @@ impl SpanQueue {
         Self {
             span_queue: FixedIndexQueue::with_capacity(1024),
             next_parent_id: SpanId::new(0),
+            active_spans: Vec::with_capacity(16),
         }
     }
 
     #[inline]
-    pub fn start_span(&mut self, event: &'static str) -> SpanHandle {
+    pub fn start_span(&mut self, event: SpanName) -> SpanHandle {
         let s = self.gen_span(self.next_parent_id, event);
         self.next_parent_id = s.id;
         let index = self.push_span(s);
+        self.active_spans.push(index);
         SpanHandle { index }
     }
 
@@ -34,10 +40,21 @@ impl SpanQueue {
         span.end_with(DefaultClock::now(), descendant_count);
 
         self.next_parent_id = span.parent_id;
+
+        if let Some(pos) = self
+            .active_spans
+            .iter()
+            .rposition(|i| *i == span_handle.index)
+        {
+            self.active_spans.remove(pos);
+        }
     }
 
     #[inline]
-    pub fn add_properties<I: IntoIterator<Item = (&'static str, String)>, F: FnOnce() -> I>(
+    pub fn add_properties<
+        I: IntoIterator<Item = (Cow<'static, str>, PropertyValue)>,
+        F: FnOnce() -> I,
+    >(
         &mut self,
         span_handle: &SpanHandle,
         properties: F,
@@ -49,7 +66,7 @@ impl SpanQueue {
     }
 
     #[inline]
-    pub fn add_property<F: FnOnce() -> (&'static str, String)>(
+    pub fn add_property<F: FnOnce() -> (Cow<'static, str>, PropertyValue)>(
         &mut self,
         span_handle: &SpanHandle,
         property: F,
@@ -60,11 +77,58 @@ impl SpanQueue {
         span.properties.push(property());
     }
 
+    #[inline]
+    pub fn add_log<I: IntoIterator<Item = (Cow<'static, str>, PropertyValue)>, F: FnOnce() -> I>(
+        &mut self,
+        span_handle: &SpanHandle,
+        event: Cow<'static, str>,
+        properties: F,
+    ) {
+        debug_assert!(self.span_queue.idx_is_valid(span_handle.index));
+
+        let cycle = DefaultClock::now();
+        let span = &mut self.span_queue[span_handle.index];
+        span.logs.push(Log {
+            cycle,
+            event,
+            properties: properties().into_iter().collect(),
+        });
+    }
+
+    /// Adds a log to the innermost unfinished span. Does nothing if there is none.
+    #[inline]
+    pub fn add_log_to_active_span<
+        I: IntoIterator<Item = (Cow<'static, str>, PropertyValue)>,
+        F: FnOnce() -> I,
+    >(
+        &mut self,
+        event: Cow<'static, str>,
+        properties: F,
+    ) {
+        let span_queue = &self.span_queue;
+        if let Some(&index) = self
+            .active_spans
+            .iter()
+            .rev()
+            .find(|i| span_queue.idx_is_valid(**i))
+        {
+            self.add_log(&SpanHandle { index }, event, properties);
+        }
+    }
+
+    #[inline]
+    pub fn set_ref_kind(&mut self, span_handle: &SpanHandle, ref_kind: RefKind) {
+        debug_assert!(self.span_queue.idx_is_valid(span_handle.index));
+
+        self.span_queue[span_handle.index].ref_kind = ref_kind;
+    }
+
     #[inline]
     pub fn start_scope_span(
         &mut self,
-        placeholder_event: &'static str,
-        event: &'static str,
+        placeholder_event: SpanName,
+        event: SpanName,
+        ref_kind: RefKind,
     ) -> ScopeSpan {
         // add a spawn span for indirectly linking to the external span
         let mut s = self.gen_span(self.next_parent_id, placeholder_event);
@@ -74,7 +138,7 @@ impl SpanQueue {
         let es_parent = s.id;
         self.push_span(s);
 
-        self.gen_scope_span(es_parent, event, cycle)
+        self.gen_scope_span(es_parent, ref_kind, event, cycle)
     }
 
     #[inline]
@@ -85,6 +149,7 @@ impl SpanQueue {
     #[inline]
     pub fn remove_before(&mut self, index: usize) {
         self.span_queue.remove_before(index);
+        self.prune_active_spans();
     }
 
     #[inline]
@@ -94,13 +159,15 @@ impl SpanQueue {
 
     #[inline]
     pub fn take_queue_from(&mut self, index: usize) -> VecDeque<Span> {
-        self.span_queue.take_queue_from(index)
+        let spans = self.span_queue.take_queue_from(index);
+        self.prune_active_spans();
+        spans
     }
 }
 
 impl SpanQueue {
     #[inline]
-    fn gen_span(&self, parent_id: SpanId, event: &'static str) -> Span {
+    fn gen_span(&self, parent_id: SpanId, event: SpanName) -> Span {
         Span::begin_with(
             DefaultIdGenerator::next_id(),
             parent_id,
@@ -113,10 +180,17 @@ impl SpanQueue {
     fn gen_scope_span(
         &self,
         parent_id: SpanId,
-        event: &'static str,
+        ref_kind: RefKind,
+        event: SpanName,
         begin_cycle: Cycle,
     ) -> ScopeSpan {
-        ScopeSpan::new(DefaultIdGenerator::next_id(), parent_id, begin_cycle, event)
+        ScopeSpan::new(
+            DefaultIdGenerator::next_id(),
+            parent_id,
+            ref_kind,
+            begin_cycle,
+            event,
+        )
     }
 
     #[inline]
@@ -124,6 +198,13 @@ impl SpanQueue {
         self.span_queue.push_back(span)
     }
 
+    /// Forgets the unfinished spans no longer held by the queue, e.g. those whose guards
+    /// outlived their scope or were leaked.
+    fn prune_active_spans(&mut self) {
+        let span_queue = &self.span_queue;
+        self.active_spans.retain(|i| span_queue.idx_is_valid(*i));
+    }
+
     fn count_to_last(&self, index: usize) -> usize {
         let next_index = self.span_queue.next_index();
         next_index.wrapping_sub(index) - 1
@@ -133,3 +214,28 @@ impl SpanQueue {
 pub struct SpanHandle {
     pub(self) index: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_active_spans() {
+        let mut queue = SpanQueue::new();
+        // the guard of this span outlives its scope
+        let _outlived = queue.start_span("outlived".into());
+        assert_eq!(queue.take_queue_from(0).len(), 1);
+        assert!(queue.active_spans.is_empty());
+
+        queue.start_span("span".into());
+        queue.start_span("leaked".into());
+        queue.add_log_to_active_span("event".into(), Vec::new);
+        queue.remove_before(2);
+        assert_eq!(queue.active_spans, vec![2]);
+
+        queue.add_log_to_active_span("event".into(), Vec::new);
+        let spans = queue.take_queue_from(2);
+        assert_eq!(spans[0].logs.len(), 2);
+        assert!(queue.active_spans.is_empty());
+    }
+}
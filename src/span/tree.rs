@@ -0,0 +1,56 @@
+use crate::span::span_id::SpanId;
+use crate::span::Span;
+use std::collections::HashMap;
+
+/// The parent-child structure of a collected trace, rebuilt from `id` and `parent_id`.
+///
+/// A span whose parent is not among the spans, e.g. the root span, becomes a root of the tree.
+/// Roots and children are ordered by `begin_cycle`.
+pub struct SpanTree<'a> {
+    spans: &'a [Span],
+    roots: Vec<usize>,
+    children: Vec<Vec<usize>>,
+}
+
+impl<'a> SpanTree<'a> {
+    pub fn new(spans: &'a [Span]) -> Self {
+        let index_of: HashMap<SpanId, usize> =
+            spans.iter().enumerate().map(|(i, s)| (s.id, i)).collect();
+
+        let mut roots = vec![];
+        let mut children = vec![vec![]; spans.len()];
+        for (i, span) in spans.iter().enumerate() {
+            match index_of.get(&span.parent_id) {
+                Some(&parent) if parent != i => children[parent].push(i),
+                _ => roots.push(i),
+            }
+        }
+
+        let by_begin = |i: &usize| spans[*i].begin_cycle;
+        roots.sort_by_key(by_begin);
+        for c in children.iter_mut() {
+            c.sort_by_key(by_begin);
+        }
+
+        SpanTree {
+            spans,
+            roots,
+            children,
+        }
+    }
+
+    #[inline]
+    pub fn span(&self, index: usize) -> &'a Span {
+        &self.spans[index]
+    }
+
+    #[inline]
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    #[inline]
+    pub fn children(&self, index: usize) -> &[usize] {
+        &self.children[index]
+    }
+}
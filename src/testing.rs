@@ -0,0 +1,150 @@
+//! Helpers for asserting the shape of collected traces in tests.
+//!
+//! Ids, timings and properties are ignored, and so is the order of siblings, which is not
+//! deterministic for spans recorded on different threads.
+//!
+//! ```
+//! use batch_tracing::testing::{assert_shape, Tree};
+//! use batch_tracing::{new_span, root_scope, CollectOptions};
+//!
+//! let (scope, collector) = root_scope("root");
+//! {
+//!     let _sg = scope.start_scope();
+//!     let _g = new_span("a");
+//!     let _g = new_span("b");
+//! }
+//! drop(scope);
+//! let spans = collector.collect(CollectOptions::new().with_sync(true)).spans;
+//!
+//! assert_shape(
+//!     &spans,
+//!     &Tree::new("root", vec![Tree::new("a", vec![Tree::leaf("b")])]),
+//! );
+//! ```
+
+use crate::span::tree::SpanTree;
+use crate::Span;
+#[cfg(test)]
+use crate::{root_scope, CollectOptions, Trace};
+use std::fmt;
+
+/// A span and its children, identified by event names only.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Tree {
+    pub event: String,
+    pub children: Vec<Tree>,
+}
+
+impl Tree {
+    pub fn new(event: impl Into<String>, children: Vec<Tree>) -> Self {
+        Tree {
+            event: event.into(),
+            children,
+        }
+        .sorted()
+    }
+
+    pub fn leaf(event: impl Into<String>) -> Self {
+        Self::new(event, vec![])
+    }
+
+    /// Rebuilds the trees of collected spans, one per span without a parent among them.
+    pub fn from_spans(spans: &[Span]) -> Vec<Tree> {
+        let tree = SpanTree::new(spans);
+        let mut roots: Vec<_> = tree
+            .roots()
+            .iter()
+            .map(|&i| Self::from_span_tree(&tree, i))
+            .collect();
+        roots.sort();
+        roots
+    }
+
+    /// Returns the number of spans in the tree.
+    pub fn span_count(&self) -> usize {
+        1 + self.children.iter().map(Tree::span_count).sum::<usize>()
+    }
+
+    fn from_span_tree(tree: &SpanTree, index: usize) -> Tree {
+        Tree::new(
+            tree.span(index).event.as_ref(),
+            tree.children(index)
+                .iter()
+                .map(|&c| Self::from_span_tree(tree, c))
+                .collect(),
+        )
+    }
+
+    fn sorted(mut self) -> Self {
+        self.children.sort();
+        self
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(f, "{:indent$}{}", "", self.event, indent = depth * 4)?;
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints one event per line, indented by depth, with siblings in sorted order.
+impl fmt::Display for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Asserts that the spans form exactly the `expected` tree.
+///
+/// # Panics
+///
+/// Panics with both trees printed if the spans have a different shape or more than one root.
+pub fn assert_shape(spans: &[Span], expected: &Tree) {
+    let actual = Tree::from_spans(spans);
+    if actual.len() == 1 && actual[0] == *expected {
+        return;
+    }
+
+    let actual: String = actual.iter().map(|t| t.to_string()).collect();
+    panic!(
+        "trace shape mismatch\nexpected:\n{}actual:\n{}",
+        expected, actual
+    );
+}
+
+/// Runs `f` in a local scope of a new trace rooted at "root", and collects the trace.
+#[cfg(test)]
+pub(crate) fn trace_of(f: impl FnOnce()) -> Trace {
+    let (scope, collector) = root_scope("root");
+    {
+        let _sg = scope.start_scope();
+        f();
+    }
+    drop(scope);
+    collector.collect(CollectOptions::new().with_sync(true))
+}
+
+/// Like [`trace_of`], returning the spans only.
+#[cfg(test)]
+pub(crate) fn spans_of(f: impl FnOnce()) -> Vec<Span> {
+    trace_of(f).spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_span;
+
+    #[test]
+    #[should_panic(expected = "trace shape mismatch")]
+    fn mismatch() {
+        let spans = spans_of(|| {
+            let _g = new_span("a");
+        });
+
+        assert_eq!(Tree::from_spans(&spans)[0].span_count(), 2);
+        assert_shape(&spans, &Tree::new("root", vec![Tree::leaf("b")]));
+    }
+}
@@ -1,9 +1,11 @@
 use crate::span::cycle::DefaultClock;
 use crate::span::span_id::SpanId;
 use crate::span::{ScopeSpan, Span};
+use crate::trace::scope::TraceOptions;
 use crossbeam_channel::Sender;
+use futures_03::task::AtomicWaker;
 use std::collections::VecDeque;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 #[derive(Clone, Debug)]
@@ -11,23 +13,92 @@ pub enum SpanCollection {
     LocalSpans {
         spans: VecDeque<Span>,
         parent_span_id: SpanId,
+        /// Spans of the local scope that were not recorded because of span limits
+        dropped_spans: usize,
     },
     ScopeSpan(Span),
 }
 
+/// The sending half of the channel of a trace. Dropping it disconnects the channel and wakes
+/// a pending `Collector::collect_async`.
+#[derive(Debug)]
+pub struct SpanSender {
+    sender: Option<Sender<SpanCollection>>,
+    waker: Arc<AtomicWaker>,
+
+    // local spans the trace may still record, spawn and scope spans are not counted
+    remaining_spans: AtomicUsize,
+
+    // local spans a thread may hold for a scope of the trace
+    max_spans_per_line: usize,
+
+    // whether either of the limits above is set
+    limited: bool,
+}
+
+impl SpanSender {
+    pub fn new(
+        sender: Sender<SpanCollection>,
+        waker: Arc<AtomicWaker>,
+        options: &TraceOptions,
+    ) -> Self {
+        SpanSender {
+            sender: Some(sender),
+            waker,
+            remaining_spans: AtomicUsize::new(options.max_spans),
+            max_spans_per_line: options.max_spans_per_line,
+            limited: options.max_spans != usize::MAX || options.max_spans_per_line != usize::MAX,
+        }
+    }
+
+    fn send(&self, span_collection: SpanCollection) {
+        if let Some(sender) = &self.sender {
+            sender.send(span_collection).ok();
+        }
+    }
+
+    /// Takes a span from the budget of the trace, returning `false` if none is left.
+    #[inline]
+    fn take_span(&self) -> bool {
+        if self.remaining_spans.load(Ordering::Relaxed) == usize::MAX {
+            return true;
+        }
+
+        self.remaining_spans
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |r| r.checked_sub(1))
+            .is_ok()
+    }
+
+    fn return_span(&self) {
+        if self.remaining_spans.load(Ordering::Relaxed) == usize::MAX {
+            return;
+        }
+
+        self.remaining_spans.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Drop for SpanSender {
+    fn drop(&mut self) {
+        // disconnect before waking, so that the collector observes it when polled
+        self.sender.take();
+        self.waker.wake();
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Acquirer {
-    sender: Arc<Sender<SpanCollection>>,
+    sender: Arc<SpanSender>,
     closed: Arc<AtomicBool>,
 }
 
 impl Acquirer {
-    pub fn new(sender: Arc<Sender<SpanCollection>>, closed: Arc<AtomicBool>) -> Self {
+    pub fn new(sender: Arc<SpanSender>, closed: Arc<AtomicBool>) -> Self {
         Acquirer { sender, closed }
     }
 
     pub fn submit(&self, span_collection: SpanCollection) {
-        self.sender.send(span_collection).ok();
+        self.sender.send(span_collection);
     }
 
     pub fn is_shutdown(&self) -> bool {
@@ -40,6 +111,9 @@ pub struct AcquirerGroup {
     /// A span represents task processing
     scope_span: ScopeSpan,
     acquirers: Vec<Acquirer>,
+
+    /// Whether any trace of the group limits its spans
+    limited: bool,
 }
 
 impl AcquirerGroup {
@@ -47,6 +121,7 @@ impl AcquirerGroup {
         debug_assert!(!acquirers.is_empty());
 
         AcquirerGroup {
+            limited: Self::any_limited(&acquirers),
             scope_span: span,
             acquirers,
         }
@@ -73,16 +148,54 @@ impl AcquirerGroup {
             None
         } else {
             Some(Self {
+                limited: Self::any_limited(&acquirers),
                 scope_span,
                 acquirers,
             })
         }
     }
 
-    pub fn submit(&self, spans: VecDeque<Span>) {
+    #[inline]
+    pub fn is_limited(&self) -> bool {
+        self.limited
+    }
+
+    /// Takes a span from the budget of every trace the groups report to, returning `false`
+    /// without taking any if one of them has none left. Each group comes with the number of
+    /// spans the thread holds for it, which must stay below the limit per line of its traces.
+    pub fn take_span<'a, I: Iterator<Item = (&'a AcquirerGroup, usize)> + Clone>(iter: I) -> bool {
+        let line_is_full = iter.clone().any(|(s, spans_held)| {
+            s.acquirers
+                .iter()
+                .any(|acq| spans_held >= acq.sender.max_spans_per_line)
+        });
+        if line_is_full {
+            return false;
+        }
+
+        // a trace may be reached through several groups, e.g. nested scopes of one thread
+        let mut taken: Vec<&SpanSender> = Vec::new();
+        for (s, _) in iter {
+            for acq in &s.acquirers {
+                let sender = acq.sender.as_ref();
+                if !sender.limited || taken.iter().any(|t| std::ptr::eq(*t, sender)) {
+                    continue;
+                }
+                if !sender.take_span() {
+                    taken.iter().for_each(|t| t.return_span());
+                    return false;
+                }
+                taken.push(sender);
+            }
+        }
+        true
+    }
+
+    pub fn submit(&self, spans: VecDeque<Span>, dropped_spans: usize) {
         self.submit_to_acquirers(SpanCollection::LocalSpans {
             spans,
             parent_span_id: self.scope_span.id,
+            dropped_spans,
         });
     }
 
@@ -92,6 +205,10 @@ impl AcquirerGroup {
 }
 
 impl AcquirerGroup {
+    fn any_limited(acquirers: &[Acquirer]) -> bool {
+        acquirers.iter().any(|acq| acq.sender.limited)
+    }
+
     fn submit_to_acquirers(&self, span_collection: SpanCollection) {
         // save one clone
         for acq in self.acquirers.iter().skip(1) {
@@ -1,59 +1,290 @@
-use crate::span::cycle::DefaultClock;
-use crate::span::span_id::SpanId;
-use crate::span::Span;
+use crate::sampling::{LatencyThreshold, TailSampler};
+use crate::span::span_id::{SpanId, TraceId};
+use crate::span::{PropertyValue, Span};
 use crate::trace::acquirer::SpanCollection;
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, RecvTimeoutError, TryRecvError};
+use futures_03::future::{self, Either};
+use futures_03::pin_mut;
+use futures_03::task::AtomicWaker;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::task::{Context, Poll};
+use std::thread::Thread;
+use std::time::{Duration, Instant};
+
+/// Options of [`Collector::collect`].
+#[derive(Clone, Default)]
+pub struct CollectOptions {
+    sync: bool,
+    timeout: Option<Duration>,
+    duration_threshold: Option<Duration>,
+    parent_id_of_root: Option<SpanId>,
+    tail_sampler: Option<Arc<dyn TailSampler>>,
+}
+
+impl CollectOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Waits until all scopes of the trace are dropped instead of taking only the spans
+    /// submitted so far. Disabled by default. An asynchronous collect waits without blocking.
+    pub fn with_sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Bounds how long a synchronous collect, blocking or asynchronous, waits for the scopes of
+    /// the trace. When the timeout expires, the collector is closed and the trace is marked
+    /// incomplete.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Keeps all spans only if the root span lasts at least `duration_threshold`, otherwise
+    /// keeps the root span alone.
+    pub fn with_duration_threshold(mut self, duration_threshold: Duration) -> Self {
+        self.duration_threshold = Some(duration_threshold);
+        self
+    }
+
+    /// Sets the parent of the root span, e.g. to attach the trace to a span of an upstream
+    /// service.
+    pub fn with_parent_id_of_root(mut self, parent_id_of_root: SpanId) -> Self {
+        self.parent_id_of_root = Some(parent_id_of_root);
+        self
+    }
+
+    /// Keeps all spans only if `tail_sampler` samples the collected trace, otherwise keeps the
+    /// root span alone.
+    pub fn with_tail_sampler(mut self, tail_sampler: impl TailSampler + 'static) -> Self {
+        self.tail_sampler = Some(Arc::new(tail_sampler));
+        self
+    }
+}
+
+impl fmt::Debug for CollectOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CollectOptions")
+            .field("sync", &self.sync)
+            .field("timeout", &self.timeout)
+            .field("duration_threshold", &self.duration_threshold)
+            .field("parent_id_of_root", &self.parent_id_of_root)
+            .field("tail_sampler", &self.tail_sampler.is_some())
+            .finish()
+    }
+}
+
+/// The spans of a trace returned by [`Collector::collect`].
+#[derive(Clone, Debug)]
+pub struct Trace {
+    pub trace_id: TraceId,
+    pub spans: Vec<Span>,
+    pub stats: TraceStats,
+    root_id: Option<SpanId>,
+}
+
+impl Trace {
+    /// Returns the span of the root scope, or `None` if it was not submitted before an
+    /// unsynchronized collect.
+    pub fn root_span(&self) -> Option<&Span> {
+        let root_id = self.root_id?;
+        self.spans.iter().find(|s| s.id == root_id)
+    }
+}
+
+/// What happened to the spans of a trace while collecting it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TraceStats {
+    /// Spans dropped because they were still running when their scope ended.
+    pub unfinished_dropped: usize,
+    /// Whether all spans but the root were dropped because the trace ended within the
+    /// duration threshold.
+    pub pruned_by_threshold: bool,
+    /// Whether all spans but the root were dropped because the tail sampler rejected the trace.
+    pub pruned_by_sampler: bool,
+    /// Spans dropped because the limits set by `TraceOptions` were exceeded. Also recorded in
+    /// the `spans_dropped` property of the root span.
+    pub spans_dropped: usize,
+    /// Whether a synchronous collect timed out, or the deadline given to `collect_async_until`
+    /// passed, before all scopes of the trace were dropped.
+    pub incomplete: bool,
+}
 
 pub struct Collector {
+    trace_id: TraceId,
     receiver: Receiver<SpanCollection>,
     closed: Arc<AtomicBool>,
+    waker: Arc<AtomicWaker>,
+    pending: Pending,
+}
+
+/// What received spans still lack before they can be emitted, kept across drains.
+#[derive(Debug, Default)]
+struct Pending {
+    root_span: Option<Span>,
+    scope_spans: Vec<Span>,
+    parent_ids_of_spawn_spans: HashMap<SpanId, SpanId>,
+    unfinished_dropped: usize,
+    spans_dropped: usize,
 }
 
 impl Collector {
+    /// Returns the id of the trace whose spans this collector receives.
+    #[inline]
+    pub fn trace_id(&self) -> TraceId {
+        self.trace_id
+    }
+
     /// Collects spans from traced routines.
     ///
-    /// If passing `duration_threshold`, all spans will be reserved only when duration of the root
-    /// span exceeds `duration_threshold`, otherwise only one span, the root span, will be returned.
-    pub fn collect(
+    /// If a duration threshold is set, all spans will be reserved only when duration of the root
+    /// span exceeds it, otherwise only one span, the root span, will be returned.
+    pub fn collect(self, options: CollectOptions) -> Trace {
+        let mut incomplete = false;
+        let span_collections: Vec<_> = match (options.sync, options.timeout) {
+            (true, Some(timeout)) => {
+                let deadline = Instant::now() + timeout;
+                let mut span_collections = vec![];
+                loop {
+                    match self.receiver.recv_deadline(deadline) {
+                        Ok(span_collection) => span_collections.push(span_collection),
+                        Err(RecvTimeoutError::Timeout) => {
+                            incomplete = true;
+                            break;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                span_collections
+            }
+            (true, None) => self.receiver.iter().collect(),
+            (false, _) => self.receiver.try_iter().collect(),
+        };
+
+        self.finish(span_collections, incomplete, options)
+    }
+
+    /// Takes the spans finished so far without closing the collector, so that long-running
+    /// traces can be reported in parts.
+    ///
+    /// Spans of scopes still running stay in their threads, and spawned scopes wait here for
+    /// the span they were spawned from. The root span is only returned by the final collect,
+    /// which alone applies the duration threshold and the statistics, even if the root span
+    /// was received by a drain. Spans already drained are not pruned by the threshold.
+    pub fn drain(&mut self) -> Vec<Span> {
+        let span_collections = self.receiver.try_iter().collect();
+        self.remove_unfinished_and_spawn_spans(span_collections)
+    }
+
+    /// Like `collect`, but waits for the scopes of a synchronous collect without blocking the
+    /// thread.
+    ///
+    /// The timeout is timed by a thread of its own, so the future can run on any executor.
+    pub async fn collect_async(self, options: CollectOptions) -> Trace {
+        match (options.sync, options.timeout) {
+            (true, Some(timeout)) => self.collect_async_until(options, Delay::new(timeout)).await,
+            (true, None) => self.collect_async_until(options, future::pending()).await,
+            (false, _) => self.collect(options),
+        }
+    }
+
+    /// Waits for all scopes of the trace like a synchronous `collect_async`, but resolves with
+    /// the spans submitted so far once `deadline` resolves, marking the trace incomplete. The
+    /// deadline is usually a timer of the executor running the future, e.g.
+    /// `tokio::time::delay_for`. `with_sync` and `with_timeout` are ignored.
+    pub async fn collect_async_until(
         self,
-        need_sync: bool,
-        duration_threshold: Option<Duration>,
-        parent_id_of_root: Option<SpanId>,
-    ) -> Vec<Span> {
-        let span_collections: Vec<_> = if need_sync {
-            self.receiver.iter().collect()
-        } else {
-            self.receiver.try_iter().collect()
+        options: CollectOptions,
+        deadline: impl Future<Output = ()>,
+    ) -> Trace {
+        let mut span_collections = vec![];
+        let receive = Receive {
+            receiver: &self.receiver,
+            waker: &self.waker,
+            span_collections: &mut span_collections,
         };
+        pin_mut!(deadline);
+        let incomplete = match future::select(receive, deadline).await {
+            Either::Left(_) => false,
+            Either::Right(_) => true,
+        };
+
+        self.finish(span_collections, incomplete, options)
+    }
+}
+
+impl Collector {
+    fn finish(
+        mut self,
+        span_collections: Vec<SpanCollection>,
+        incomplete: bool,
+        options: CollectOptions,
+    ) -> Trace {
         self.closed.store(true, Ordering::SeqCst);
 
-        if let Some(duration) = duration_threshold {
-            if let Some(span) = span_collections.iter().find_map(|s| match s {
-                SpanCollection::ScopeSpan(s) => Some(s.clone()),
-                _ => None,
-            }) {
-                let anchor = DefaultClock::anchor();
-                let duration_ns = DefaultClock::cycle_to_realtime(span.end_cycle, anchor).ns
-                    - DefaultClock::cycle_to_realtime(span.begin_cycle, anchor).ns;
-                if duration_ns < duration.as_nanos() as _ {
-                    return vec![span];
-                }
+        let mut spans = self.remove_unfinished_and_spawn_spans(span_collections);
+
+        // whatever is still pending will not get any more complete
+        for mut span in self.pending.scope_spans.drain(..) {
+            if let Some(parent_id) = self.pending.parent_ids_of_spawn_spans.get(&span.parent_id) {
+                span.parent_id = *parent_id;
             }
+            spans.push(span);
         }
+        let spans_dropped = self.pending.spans_dropped;
+        let root_id = self.pending.root_span.take().map(|mut root_span| {
+            root_span.parent_id = options.parent_id_of_root.unwrap_or_default();
+            if spans_dropped > 0 {
+                root_span.properties.push((
+                    Cow::Borrowed("spans_dropped"),
+                    PropertyValue::from(spans_dropped),
+                ));
+            }
+            let root_id = root_span.id;
+            spans.push(root_span);
+            root_id
+        });
 
-        Self::remove_unfinished_and_spawn_spans(span_collections, parent_id_of_root)
+        let mut trace = Trace {
+            trace_id: self.trace_id,
+            spans,
+            stats: TraceStats {
+                unfinished_dropped: self.pending.unfinished_dropped,
+                spans_dropped,
+                incomplete,
+                ..TraceStats::default()
+            },
+            root_id,
+        };
+        if let Some(duration_threshold) = options.duration_threshold {
+            if !LatencyThreshold(duration_threshold).sample(&trace) {
+                trace.spans.retain(|s| Some(s.id) == root_id);
+                trace.stats.pruned_by_threshold = true;
+                return trace;
+            }
+        }
+        if let Some(tail_sampler) = &options.tail_sampler {
+            if !tail_sampler.sample(&trace) {
+                trace.spans.retain(|s| Some(s.id) == root_id);
+                trace.stats.pruned_by_sampler = true;
+            }
+        }
+        trace
     }
-}
 
-impl Collector {
+    /// Turns span collections into spans, keeping scope spans whose spawn span has not arrived
+    /// yet and the root span pending.
     #[inline]
     fn remove_unfinished_and_spawn_spans(
+        &mut self,
         span_collections: Vec<SpanCollection>,
-        parent_id_of_root: Option<SpanId>,
     ) -> Vec<Span> {
         let capacity = span_collections
             .iter()
@@ -64,56 +295,64 @@ impl Collector {
             .sum();
 
         let mut spans = Vec::with_capacity(capacity);
-        let mut pending_scope_spans = Vec::with_capacity(span_collections.len());
-        let mut parent_ids_of_spawn_spans = HashMap::with_capacity(span_collections.len());
+        let pending = &mut self.pending;
 
         for span_collection in span_collections {
             match span_collection {
                 SpanCollection::LocalSpans {
                     spans: local_spans,
                     parent_span_id,
+                    dropped_spans,
                 } => {
+                    pending.spans_dropped += dropped_spans;
                     let mut remaining_descendant_count = 0;
                     for mut span in local_spans {
                         if remaining_descendant_count > 0 {
                             remaining_descendant_count -= 1;
                             if span._is_spawn_span {
-                                parent_ids_of_spawn_spans.insert(span.id, span.parent_id);
+                                pending
+                                    .parent_ids_of_spawn_spans
+                                    .insert(span.id, span.parent_id);
                                 continue;
                             }
 
                             spans.push(span);
                         } else if span.end_cycle.is_zero() {
+                            pending.unfinished_dropped += 1;
                             continue;
                         } else {
                             span.parent_id = parent_span_id;
 
                             if span._is_spawn_span {
-                                parent_ids_of_spawn_spans.insert(span.id, span.parent_id);
+                                pending
+                                    .parent_ids_of_spawn_spans
+                                    .insert(span.id, span.parent_id);
                                 continue;
                             }
 
-                            remaining_descendant_count = span._descendant_count;
+                            remaining_descendant_count = span._descendant_count as usize;
                             spans.push(span);
                         }
                     }
                 }
-                SpanCollection::ScopeSpan(mut scope_span) => {
+                SpanCollection::ScopeSpan(scope_span) => {
                     if scope_span.is_root() {
-                        scope_span.parent_id = parent_id_of_root.unwrap_or_default();
-                        spans.push(scope_span);
+                        pending.root_span = Some(scope_span);
                     } else {
-                        pending_scope_spans.push(scope_span);
+                        pending.scope_spans.push(scope_span);
                     }
                 }
             }
         }
 
-        for mut span in pending_scope_spans {
-            if let Some(parent_id) = parent_ids_of_spawn_spans.get(&span.parent_id) {
-                span.parent_id = *parent_id;
+        for mut span in std::mem::take(&mut pending.scope_spans) {
+            match pending.parent_ids_of_spawn_spans.remove(&span.parent_id) {
+                Some(parent_id) => {
+                    span.parent_id = parent_id;
+                    spans.push(span);
+                }
+                None => pending.scope_spans.push(span),
             }
-            spans.push(span);
         }
 
         spans
@@ -121,7 +360,343 @@ impl Collector {
 }
 
 impl Collector {
-    pub(crate) fn new(receiver: Receiver<SpanCollection>, closed: Arc<AtomicBool>) -> Self {
-        Collector { receiver, closed }
+    pub(crate) fn new(
+        trace_id: TraceId,
+        receiver: Receiver<SpanCollection>,
+        closed: Arc<AtomicBool>,
+        waker: Arc<AtomicWaker>,
+    ) -> Self {
+        Collector {
+            trace_id,
+            receiver,
+            closed,
+            waker,
+            pending: Pending::default(),
+        }
+    }
+}
+
+/// Receives span collections until the channel is disconnected.
+struct Receive<'a> {
+    receiver: &'a Receiver<SpanCollection>,
+    waker: &'a AtomicWaker,
+    span_collections: &'a mut Vec<SpanCollection>,
+}
+
+impl Future for Receive<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.waker.register(cx.waker());
+        loop {
+            match self.receiver.try_recv() {
+                Ok(span_collection) => self.span_collections.push(span_collection),
+                Err(TryRecvError::Empty) => return Poll::Pending,
+                Err(TryRecvError::Disconnected) => return Poll::Ready(()),
+            }
+        }
+    }
+}
+
+/// Resolves once a timeout expires. The timeout is timed by a thread that parks until then,
+/// or until the delay is dropped.
+struct Delay {
+    state: Arc<DelayState>,
+    thread: Thread,
+}
+
+#[derive(Default)]
+struct DelayState {
+    expired: AtomicBool,
+    dropped: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl Delay {
+    fn new(timeout: Duration) -> Self {
+        let deadline = Instant::now() + timeout;
+        let state = Arc::new(DelayState::default());
+        let handle = {
+            let state = state.clone();
+            std::thread::Builder::new()
+                .name("collect-timeout".to_owned())
+                .spawn(move || {
+                    while !state.dropped.load(Ordering::SeqCst) {
+                        let now = Instant::now();
+                        if now >= deadline {
+                            state.expired.store(true, Ordering::SeqCst);
+                            state.waker.wake();
+                            return;
+                        }
+                        std::thread::park_timeout(deadline - now);
+                    }
+                })
+                .expect("failed to spawn the collect timeout thread")
+        };
+
+        Delay {
+            state,
+            thread: handle.thread().clone(),
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.state.waker.register(cx.waker());
+        if self.state.expired.load(Ordering::SeqCst) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        self.state.dropped.store(true, Ordering::SeqCst);
+        self.thread.unpark();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::HasError;
+    use crate::{new_span, root_scope, spawn_scope};
+
+    #[test]
+    fn stats() {
+        let (scope, collector) = root_scope("root");
+        {
+            let _sg = scope.start_scope();
+            drop(new_span("finished"));
+            std::mem::forget(new_span("unfinished"));
+        }
+        drop(scope);
+        let trace_id = collector.trace_id();
+        let trace = collector.collect(CollectOptions::new().with_sync(true));
+
+        assert_eq!(trace.trace_id, trace_id);
+        assert_eq!(trace.root_span().unwrap().event, "root");
+        assert_eq!(trace.spans.len(), 2);
+        assert_eq!(
+            trace.stats,
+            TraceStats {
+                unfinished_dropped: 1,
+                pruned_by_threshold: false,
+                pruned_by_sampler: false,
+                spans_dropped: 0,
+                incomplete: false,
+            }
+        );
+    }
+
+    #[test]
+    fn pruned_by_threshold() {
+        let (scope, collector) = root_scope("root");
+        {
+            let _sg = scope.start_scope();
+            let _g = new_span("child");
+        }
+        drop(scope);
+        let trace = collector.collect(
+            CollectOptions::new()
+                .with_sync(true)
+                .with_duration_threshold(Duration::from_secs(60))
+                .with_parent_id_of_root(SpanId::new(42)),
+        );
+
+        assert!(trace.stats.pruned_by_threshold);
+        assert_eq!(trace.spans.len(), 1);
+        assert_eq!(trace.root_span().unwrap().event, "root");
+        assert_eq!(trace.root_span().unwrap().parent_id, SpanId::new(42));
+    }
+
+    #[test]
+    fn pruned_by_threshold_with_spawned_scope() {
+        let (scope, collector) = root_scope("root");
+        let spawned = {
+            let _sg = scope.start_scope();
+            std::mem::forget(new_span("unfinished"));
+            spawn_scope("spawned")
+        };
+        // the spawned scope is submitted before the root scope
+        drop(spawned);
+        drop(scope);
+        let trace = collector.collect(
+            CollectOptions::new()
+                .with_sync(true)
+                .with_duration_threshold(Duration::from_secs(60))
+                .with_parent_id_of_root(SpanId::new(42)),
+        );
+
+        assert!(trace.stats.pruned_by_threshold);
+        assert_eq!(trace.stats.unfinished_dropped, 1);
+        assert_eq!(trace.spans.len(), 1);
+        let root_span = trace.root_span().unwrap();
+        assert_eq!(root_span.event, "root");
+        assert_eq!(root_span.parent_id, SpanId::new(42));
+    }
+
+    #[test]
+    fn timeout() {
+        let (scope, collector) = root_scope("root");
+        {
+            let _sg = scope.start_scope();
+            let _g = new_span("child");
+        }
+        let trace = collector.collect(
+            CollectOptions::new()
+                .with_sync(true)
+                .with_timeout(Duration::from_millis(10)),
+        );
+        drop(scope);
+
+        assert!(trace.stats.incomplete);
+        assert_eq!(trace.spans.len(), 1);
+        assert!(trace.root_span().is_none());
+    }
+
+    #[tokio::test]
+    async fn collect_async() {
+        let (scope, collector) = root_scope("root");
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            let _sg = scope.start_scope();
+            let _g = new_span("child");
+        });
+        let trace = collector
+            .collect_async(CollectOptions::new().with_sync(true))
+            .await;
+        handle.join().unwrap();
+
+        assert!(!trace.stats.incomplete);
+        assert_eq!(trace.spans.len(), 2);
+        assert_eq!(trace.root_span().unwrap().event, "root");
+    }
+
+    #[test]
+    fn collect_async_timeout() {
+        let (scope, collector) = root_scope("root");
+        {
+            let _sg = scope.start_scope();
+            let _g = new_span("child");
+        }
+        // runs on an executor without timers
+        let trace = futures_03::executor::block_on(
+            collector.collect_async(
+                CollectOptions::new()
+                    .with_sync(true)
+                    .with_timeout(Duration::from_millis(10)),
+            ),
+        );
+        drop(scope);
+
+        assert!(trace.stats.incomplete);
+        assert_eq!(trace.spans.len(), 1);
+        assert!(trace.root_span().is_none());
+    }
+
+    #[test]
+    fn collect_async_until() {
+        let (scope, collector) = root_scope("root");
+        let trace = futures_03::executor::block_on(
+            collector.collect_async_until(CollectOptions::new(), future::ready(())),
+        );
+        drop(scope);
+
+        assert!(trace.stats.incomplete);
+        assert!(trace.spans.is_empty());
+    }
+
+    #[test]
+    fn drain() {
+        let (scope, mut collector) = root_scope("root");
+        let spawned = {
+            let _sg = scope.start_scope();
+            let _g = new_span("first");
+            spawn_scope("spawned")
+        };
+
+        let drained = collector.drain();
+        let events: Vec<_> = drained.iter().map(|s| s.event.as_ref()).collect();
+        assert_eq!(events, ["first"]);
+        let first_id = drained[0].id;
+        assert!(collector.drain().is_empty());
+
+        {
+            let _sg = spawned.start_scope();
+            let _g = new_span("second");
+        }
+        drop(spawned);
+        let drained = collector.drain();
+        let second = drained.iter().find(|s| s.event == "second").unwrap();
+        let spawned = drained.iter().find(|s| s.event == "spawned").unwrap();
+        assert_eq!(second.parent_id, spawned.id);
+        assert_eq!(spawned.parent_id, first_id);
+
+        drop(scope);
+        let trace = collector.collect(CollectOptions::new().with_sync(true));
+        assert_eq!(trace.spans.len(), 1);
+        assert_eq!(trace.root_span().unwrap().event, "root");
+    }
+
+    #[test]
+    fn drain_then_threshold() {
+        let (scope, mut collector) = root_scope("root");
+        let spawned = {
+            let _sg = scope.start_scope();
+            let _g = new_span("first");
+            spawn_scope("spawned")
+        };
+        drop(scope);
+        // takes the root span from the channel
+        let drained = collector.drain();
+        let events: Vec<_> = drained.iter().map(|s| s.event.as_ref()).collect();
+        assert_eq!(events, ["first"]);
+
+        {
+            let _sg = spawned.start_scope();
+            let _g = new_span("second");
+        }
+        drop(spawned);
+        let trace = collector.collect(
+            CollectOptions::new()
+                .with_sync(true)
+                .with_duration_threshold(Duration::from_secs(60)),
+        );
+
+        assert!(trace.stats.pruned_by_threshold);
+        assert_eq!(trace.spans.len(), 1);
+        assert_eq!(trace.root_span().unwrap().event, "root");
+    }
+
+    #[test]
+    fn tail_sampler() {
+        let run = |error: bool| {
+            let (scope, collector) = root_scope("root");
+            {
+                let _sg = scope.start_scope();
+                let _g = new_span("child").with_property(|| ("error", error.into()));
+            }
+            drop(scope);
+            collector.collect(
+                CollectOptions::new()
+                    .with_sync(true)
+                    .with_tail_sampler(HasError),
+            )
+        };
+
+        let trace = run(false);
+        assert!(trace.stats.pruned_by_sampler);
+        assert_eq!(trace.spans.len(), 1);
+        assert_eq!(trace.root_span().unwrap().event, "root");
+
+        let trace = run(true);
+        assert!(!trace.stats.pruned_by_sampler);
+        assert_eq!(trace.spans.len(), 2);
     }
 }
@@ -1,34 +1,82 @@
 use crate::local::acquirer_group::registered_acquirer_group;
 use crate::local::scope_guard::LocalScopeGuard;
 use crate::span::cycle::DefaultClock;
-use crate::span::span_id::{DefaultIdGenerator, SpanId};
-use crate::span::ScopeSpan;
-use crate::trace::acquirer::{Acquirer, AcquirerGroup, SpanCollection};
-use crossbeam_channel::Sender;
+use crate::span::span_id::{DefaultIdGenerator, SpanId, TraceId};
+use crate::span::{RefKind, ScopeSpan, SpanName};
+use crate::trace::acquirer::{Acquirer, AcquirerGroup, SpanSender};
 
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
+/// Options of [`root_scope_with_options`](crate::root_scope_with_options).
+#[derive(Clone, Copy, Debug)]
+pub struct TraceOptions {
+    pub(crate) max_spans: usize,
+    pub(crate) max_spans_per_line: usize,
+}
+
+impl TraceOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Caps the local spans the trace records. Beyond the cap, `new_span` records nothing for
+    /// the trace, and the spans missed are counted in the `spans_dropped` property of the root
+    /// span. Spawned scopes are not counted, and always reach the trace. Unlimited by default.
+    pub fn with_max_spans(mut self, max_spans: usize) -> Self {
+        self.max_spans = max_spans;
+        self
+    }
+
+    /// Caps the local spans a thread holds for a running scope of the trace. Beyond the cap,
+    /// `new_span` records nothing, and the spans missed are counted like with `with_max_spans`.
+    /// Unlimited by default.
+    pub fn with_max_spans_per_line(mut self, max_spans_per_line: usize) -> Self {
+        self.max_spans_per_line = max_spans_per_line;
+        self
+    }
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        TraceOptions {
+            max_spans: usize::MAX,
+            max_spans_per_line: usize::MAX,
+        }
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct Scope {
     acquirer_group: Option<Arc<AcquirerGroup>>,
+    trace_id: Option<TraceId>,
 }
 
 impl Scope {
     pub fn start_scope(&self) -> LocalScopeGuard {
         LocalScopeGuard::new(self.acquirer_group.as_ref().cloned())
     }
+
+    /// Returns the id of the trace started by this scope.
+    ///
+    /// Only scopes returned by `root_scope` own a trace. A spawned scope may report to several
+    /// traces at once, so `None` is returned for it.
+    pub fn trace_id(&self) -> Option<TraceId> {
+        self.trace_id
+    }
 }
 
 impl Scope {
     pub(crate) fn new_root_scope(
-        event: &'static str,
-        sender: Sender<SpanCollection>,
+        trace_id: TraceId,
+        event: SpanName,
+        sender: SpanSender,
         closed: Arc<AtomicBool>,
     ) -> Self {
         let root_span = ScopeSpan::new(
             DefaultIdGenerator::next_id(),
             SpanId::new(0),
+            RefKind::ChildOf,
             DefaultClock::now(),
             event,
         );
@@ -37,12 +85,14 @@ impl Scope {
 
         Self {
             acquirer_group: Some(Arc::new(acq_group)),
+            trace_id: Some(trace_id),
         }
     }
 
-    pub(crate) fn new_scope(event: &'static str) -> Self {
+    pub(crate) fn new_scope(event: SpanName, ref_kind: RefKind) -> Self {
         Self {
-            acquirer_group: registered_acquirer_group(event).map(Arc::new),
+            acquirer_group: registered_acquirer_group(event, ref_kind).map(Arc::new),
+            trace_id: None,
         }
     }
 
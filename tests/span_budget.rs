@@ -0,0 +1,110 @@
+//! Span budgets set per trace with `TraceOptions`, recorded across threads.
+
+use batch_tracing::{
+    new_span, root_scope, root_scope_with_options, spawn_scope, CollectOptions, PropertyValue,
+    TraceOptions,
+};
+use std::collections::HashSet;
+
+fn limits() -> TraceOptions {
+    TraceOptions::new()
+        .with_max_spans_per_line(10)
+        .with_max_spans(15)
+}
+
+#[test]
+fn span_budget() {
+    let (scope, collector) = root_scope_with_options("root", limits());
+    let handles: Vec<_> = (0..2)
+        .map(|_| {
+            let scope = scope.clone();
+            std::thread::spawn(move || {
+                let _sg = scope.start_scope();
+                for _ in 0..20 {
+                    let _g = new_span("item");
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    drop(scope);
+    let trace = collector.collect(CollectOptions::new().with_sync(true));
+
+    // each thread records at most 10 of its 20 spans, and the trace takes 15 spans in all
+    assert_eq!(trace.spans.len(), 16);
+    assert_eq!(trace.stats.spans_dropped, 25);
+    assert!(trace
+        .root_span()
+        .unwrap()
+        .properties
+        .contains(&("spans_dropped".into(), PropertyValue::I64(25))));
+}
+
+#[test]
+fn spawn_scope_past_budget() {
+    let (scope, collector) = root_scope_with_options("root", limits());
+    {
+        let _sg = scope.start_scope();
+        for _ in 0..10 {
+            let _g = new_span("item");
+        }
+        for event in &["first", "second"] {
+            // the line is full, but spawn spans are not limited
+            let spawned = spawn_scope(*event);
+            std::thread::spawn(move || {
+                let _sg = spawned.start_scope();
+                for _ in 0..5 {
+                    let _g = new_span("item");
+                }
+            })
+            .join()
+            .unwrap();
+        }
+    }
+    drop(scope);
+    let trace = collector.collect(CollectOptions::new().with_sync(true));
+
+    // the first spawned scope takes the last 5 spans of the budget
+    assert_eq!(trace.stats.spans_dropped, 5);
+    assert_eq!(trace.spans.iter().filter(|s| s.event == "item").count(), 15);
+
+    let root_id = trace.root_span().unwrap().id;
+    for event in &["first", "second"] {
+        let spawned = trace.spans.iter().find(|s| s.event == *event).unwrap();
+        assert_eq!(spawned.parent_id, root_id);
+    }
+    let ids: HashSet<_> = trace.spans.iter().map(|s| s.id).collect();
+    assert!(trace
+        .spans
+        .iter()
+        .filter(|s| s.id != root_id)
+        .all(|s| ids.contains(&s.parent_id)));
+}
+
+#[test]
+fn line_budget_per_trace() {
+    let (unlimited, unlimited_collector) = root_scope("unlimited");
+    let (limited, limited_collector) = root_scope_with_options("limited", limits());
+    {
+        let _sg = unlimited.start_scope();
+        for _ in 0..20 {
+            let _g = new_span("before");
+        }
+        // the spans held for the other trace do not count against this one
+        let _sg = limited.start_scope();
+        for _ in 0..12 {
+            let _g = new_span("item");
+        }
+    }
+    drop((unlimited, limited));
+    let unlimited = unlimited_collector.collect(CollectOptions::new());
+    let limited = limited_collector.collect(CollectOptions::new());
+
+    assert_eq!(limited.spans.len(), 11);
+    assert_eq!(limited.stats.spans_dropped, 2);
+    // spans dropped for one trace are missed by every trace of the thread
+    assert_eq!(unlimited.spans.len(), 31);
+    assert_eq!(unlimited.stats.spans_dropped, 2);
+}